@@ -1,5 +1,5 @@
+use geometry::{Grid, Point, Rect};
 use std::{fs::File, io::Read, path::Path};
-use std::collections::HashMap;
 
 #[derive(Debug)]
 struct Line<'a> {
@@ -18,37 +18,47 @@ impl<'a> Line<'a> {
   }
 }
 
+fn claim_rect(pos: (usize, usize), size: (usize, usize)) -> Rect {
+  Rect::with_size(Point::new(pos.0 as i32, pos.1 as i32), size.0, size.1)
+}
+
+/// Claims per square inch of fabric: 0 unclaimed, 1 claimed once and 2 claimed more than once.
 #[derive(Debug)]
 struct Area {
-  pub area: HashMap<(usize, usize), usize>
+  pub area: Grid<usize>
 }
 
 impl Area {
   pub fn new() -> Self {
     Area {
-      area: HashMap::new()
+      area: Grid::new(Rect::new(Point::new(0, 0), Point::new(-1, -1)), 0)
+    }
+  }
+
+  /// Grows the fabric from the origin so that `rect` fits, keeping the claims made so far. Like a
+  /// `Vec`, it at least doubles along the axis it grows, so the claims are copied only a few times.
+  fn make_room(&mut self, rect: Rect) {
+    let max = self.area.bounds().max;
+    if rect.area() == 0 || (rect.max.x <= max.x && rect.max.y <= max.y) {
+      return
     }
+    let grow = |needed: i32, current: i32| if needed > current { needed.max(2 * current + 1) } else { current };
+    let grown = Rect::new(Point::new(0, 0), Point::new(grow(rect.max.x, max.x), grow(rect.max.y, max.y)));
+    let area = Grid::from_fn(grown, |point| self.area.get(point).cloned().unwrap_or(0));
+    self.area = area;
   }
 
   pub fn add_rectangle(&mut self, pos: (usize, usize), size: (usize, usize)) {
-    for x in (pos.0)..(size.0 + pos.0) {
-      for y in (pos.1)..(size.1 + pos.1) {
-        self.area.entry((x, y))
-            .and_modify(|e| { *e = 2 })
-            .or_insert(1);
-      }
+    let rect = claim_rect(pos, size);
+    self.make_room(rect);
+    for point in rect.points() {
+      let claims = &mut self.area[point];
+      *claims = if *claims == 0 { 1 } else { 2 };
     }
   }
 
   fn has_only_ones(&mut self, pos: (usize, usize), size: (usize, usize)) -> bool {
-    for x in (pos.0)..(size.0 + pos.0) {
-      for y in (pos.1)..(size.1 + pos.1) {
-        if self.area[&(x, y)] > 1 {
-          return false
-        }
-      }
-    }
-    true
+    claim_rect(pos, size).points().all(|point| self.area[point] <= 1)
   }
 
   pub fn find_rectangle_with_ones<'a>(&mut self, data: &'a Line) -> Option<&'a str> {
@@ -59,15 +69,15 @@ impl Area {
   }
 
   pub fn get_areas(&self) -> (usize, usize) {
-    self.area.iter()
-        .fold((0, 0), |acc, (_, &val)| {
+    self.area.values()
+        .fold((0, 0), |acc, &val| {
           if val == 1 { (acc.0 + 1, acc.1) } else if val == 2 { (acc.0, acc.1 + 1) } else { acc }
         })
   }
 }
 
 fn read_input_file(filename: &str) -> String {
-  let mut file = File::open(&Path::new(filename))
+  let mut file = File::open(Path::new(filename))
     .unwrap_or_else(|e| panic!("File {} read error: {}", filename, e));
   let mut buf = String::new();
   file.read_to_string(&mut buf).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
@@ -79,7 +89,7 @@ fn lines_to_vec(input: &str) -> Vec<&str> {
        .collect::<Vec<&str>>()
 }
 
-fn parse_line(line: &str) -> Line {
+fn parse_line(line: &str) -> Line<'_> {
   let elems = line.split(' ').collect::<Vec<&str>>();
   let pos = elems[2].trim_matches(':').split(',').map(|x| x.parse::<usize>().unwrap()).collect::<Vec<usize>>();
   let size = elems[3].split('x').map(|x| x.parse::<usize>().unwrap()).collect::<Vec<usize>>();
@@ -89,7 +99,7 @@ fn parse_line(line: &str) -> Line {
 fn main() {
   let data = read_input_file("./input.txt");
   let mut area = Area::new();
-  let lines = lines_to_vec(&data).iter().map(|el| parse_line(*el)).collect::<Vec<Line>>();
+  let lines = lines_to_vec(&data).iter().map(|el| parse_line(el)).collect::<Vec<Line>>();
 
   lines.iter().for_each(|el| area.add_rectangle(el.pos, el.size));
   println!("Part one: {:?}", area.get_areas());
@@ -111,6 +121,7 @@ fn main() {
 ........
 */
 #[test]
+#[allow(clippy::useless_vec, clippy::explicit_auto_deref)]
 fn basic_test() {
   let input = vec!["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"];
   let mut area = Area::new();
@@ -131,6 +142,7 @@ fn basic_test() {
 ........
 */
 #[test]
+#[allow(clippy::useless_vec, clippy::explicit_auto_deref)]
 fn rectangle_with_ones_test() {
   let input = vec!["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"];
   let mut area = Area::new();
@@ -1,116 +1,233 @@
 extern crate chrono;
 
+mod render;
+mod strategy;
+mod stream;
+mod timestamp;
+mod validate;
+
 use chrono::prelude::*;
-use std::{fs::File, io::Read, path::Path};
+use std::{env, fs::File, io, io::BufReader, io::Read, path::Path};
 use std::collections::HashMap;
+use render::{render_chart, render_csv, render_svg, shifts};
+use strategy::strategy_by_name;
+use stream::{ingest, LogStream};
+use std::fmt;
+use timestamp::{parse_timestamp, TimestampFormat, DEFAULT_FORMATS};
+use validate::{validate_log, Mode};
+
+const STREAM_WINDOW: usize = 64;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 struct Entry<'a> {
+  line: usize,
   date_time: chrono::DateTime<Utc>,
   action: &'a str,
 }
 
 impl<'a> Entry<'a> {
-  pub fn new(year: i32, month: u32, day: u32, hour: u32, minute: u32, action: &'a str) -> Self {
+  pub fn new(line: usize, date_time: DateTime<Utc>, action: &'a str) -> Self {
     Entry {
-      date_time: Utc.ymd(year, month, day).and_hms(hour, minute, 0),
+      line,
+      date_time,
       action,
     }
   }
 }
 
-struct SleepTime<'a> {
-  pub sleeps: HashMap<&'a str, u32>
+#[derive(Debug, PartialEq, Clone)]
+struct ParseError {
+  line: usize,
+  message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Action<'a> {
+  BeginShift(&'a str),
+  FallsAsleep,
+  WakesUp,
+  Unknown,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Bucketing {
+  /// Minute of the midnight hour, the puzzle semantics. Sleep outside 00:00-00:59 is not bucketed.
+  MidnightHour,
+  /// Minute of the day, 0..1440.
+  MinuteOfDay,
+  /// Quarter-hour of the day, 0..96.
+  QuarterHour,
+}
+
+impl Bucketing {
+  pub fn bucket(self, time: &DateTime<Utc>) -> Option<u32> {
+    let minute_of_day = time.hour() * 60 + time.minute();
+    match self {
+      Bucketing::MidnightHour if time.hour() == 0 => Some(time.minute()),
+      Bucketing::MidnightHour => None,
+      Bucketing::MinuteOfDay => Some(minute_of_day),
+      Bucketing::QuarterHour => Some(minute_of_day / 15),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Nap {
+  guard: String,
+  start: DateTime<Utc>,
+  end: DateTime<Utc>,
+}
+
+impl Nap {
+  pub fn minutes(&self) -> u32 {
+    (self.end - self.start).num_minutes().max(0) as u32
+  }
+
+  /// Minutes that fall into a bucket. Only differs from `minutes` for the midnight hour, where
+  /// sleep outside 00:00-00:59 is not counted.
+  pub fn bucketed_minutes(&self, bucketing: Bucketing) -> u32 {
+    calc_minute_range(&self.start, &self.end, bucketing).len() as u32
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TieBreak {
+  /// Tied winners are ordered by guard ID, then by minute.
+  LowestId,
+  /// Tied winners are ordered by minute, then by guard ID.
+  EarliestMinute,
+}
+
+impl TieBreak {
+  pub fn key(self, guard: &str, minute: u32) -> (u32, u32) {
+    match self {
+      TieBreak::LowestId => (guard_number(guard), minute),
+      TieBreak::EarliestMinute => (minute, guard_number(guard)),
+    }
+  }
 }
 
-impl<'a> SleepTime<'a> {
+struct SleepTime {
+  pub sleeps: HashMap<String, u32>,
+  pub naps: Vec<Nap>,
+  bucketing: Bucketing,
+  tie_break: TieBreak,
+  curr_quard: String,
+  bed_time: Option<DateTime<Utc>>,
+}
+
+impl SleepTime {
   pub fn new() -> Self {
     SleepTime {
-      sleeps: HashMap::new()
+      sleeps: HashMap::new(),
+      naps: vec![],
+      bucketing: Bucketing::MidnightHour,
+      tie_break: TieBreak::LowestId,
+      curr_quard: String::new(),
+      bed_time: None,
     }
   }
 
-  pub fn process_sleep_times(&mut self, log: &'a [Entry]) {
-    let mut curr_quard = "";
-    let mut is_awake = true;
-    let mut bed_time = Utc::now();
-    log.iter().for_each(|entry| {
-      let new_guard = parse_guard(entry.action);
-      let is_awake_sleep_line = new_guard.is_empty();
-      if is_awake_sleep_line {
-        is_awake = parse_awake_info(entry.action);
-        if !is_awake {
-          bed_time = entry.date_time;
-        } else {
-          let sleep_time = entry.date_time.minute() - bed_time.minute();
-          self.sleeps.entry(&curr_quard)
-            .and_modify(|e| { *e += sleep_time })
-            .or_insert(sleep_time);
-        }
-      } else {
-        curr_quard = new_guard
-      }
-    })
+  pub fn with_bucketing(mut self, bucketing: Bucketing) -> Self {
+    self.bucketing = bucketing;
+    self
+  }
+
+  pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+    self.tie_break = tie_break;
+    self
+  }
+
+  pub fn process_sleep_times(&mut self, log: &[Entry]) {
+    log.iter().for_each(|entry| self.push_entry(entry))
   }
 
-  pub fn get_sleepiest_elf(&self) -> (&str, u32) {
-    let mut biggest = ("", 0);
-    for (key, &val) in self.sleeps.iter() {
-      if val > biggest.1 {
-        biggest = (key, val)
+  /// Folds one entry into the statistics. Entries must arrive in timestamp order.
+  pub fn push_entry(&mut self, entry: &Entry) {
+    let new_guard = parse_guard(entry.action);
+    let is_awake_sleep_line = new_guard.is_empty();
+    if is_awake_sleep_line {
+      let is_awake = parse_awake_info(entry.action);
+      if !is_awake {
+        self.bed_time = Some(entry.date_time);
+      } else if let Some(start) = self.bed_time.take() {
+        let nap = Nap { guard: self.curr_quard.clone(), start, end: entry.date_time };
+        let sleep_time = nap.bucketed_minutes(self.bucketing);
+        self.sleeps.entry(self.curr_quard.clone())
+          .and_modify(|e| { *e += sleep_time })
+          .or_insert(sleep_time);
+        self.naps.push(nap);
       }
+    } else {
+      self.curr_quard = new_guard.to_string();
+      self.bed_time = None;
     }
-    biggest
   }
 
-  pub fn most_common_minute_to_sleep(&self, elf_id: &str, log: &[Entry]) -> (u32, u32) {
-    let mut sleep_times = vec![];
-    let mut curr_quard = "";
-    let mut is_awake = true;
-    let mut bed_time = Utc::now();
-    log.iter().for_each(|entry| {
-      let new_guard = parse_guard(entry.action);
-      let is_awake_sleep_line = new_guard.is_empty();
-      if curr_quard == elf_id && is_awake_sleep_line {
-        is_awake = parse_awake_info(entry.action);
-        if !is_awake {
-          bed_time = entry.date_time;
-        } else {
-          let mut times = calc_minute_range(&bed_time, &entry.date_time);
-          sleep_times.append(&mut times);
-        }
-      } else {
-        curr_quard = new_guard
-      }
-    });
+  /// Every guard sharing the most minutes asleep, ordered by ID.
+  pub fn get_sleepiest_elf(&self) -> Vec<(&str, u32)> {
+    let most = self.sleeps.values().max().cloned().unwrap_or(0);
+    let mut winners = self.sleeps.iter()
+      .filter(|&(_, &val)| val == most)
+      .map(|(key, &val)| (key.as_str(), val))
+      .collect::<Vec<_>>();
+    winners.sort_by_key(|&(guard, _)| self.tie_break.key(guard, 0));
+    winners
+  }
+
+  /// Every bucket the guard slept in most often, earliest first.
+  pub fn most_common_minute_to_sleep(&self, elf_id: &str) -> Vec<(u32, u32)> {
     let mut minutes: HashMap<u32, u32> = HashMap::new();
-    sleep_times.iter().for_each(|el| {
-      minutes.entry(*el)
-        .and_modify(|x| *x += 1)
-        .or_insert(1);
-    });
-    match minutes.iter().max_by(|(_, &a), (_, &b)| a.cmp(&b)) {
-      Some(val) => (*val.0, *val.1),
-      None => panic!("No value found")
-    }
+    self.naps.iter()
+      .filter(|nap| nap.guard == elf_id)
+      .flat_map(|nap| calc_minute_range(&nap.start, &nap.end, self.bucketing))
+      .for_each(|el| {
+        minutes.entry(el)
+          .and_modify(|x| *x += 1)
+          .or_insert(1);
+      });
+    let most = minutes.values().max().cloned().unwrap_or(0);
+    let mut winners = minutes.into_iter()
+      .filter(|&(_, count)| count == most)
+      .collect::<Vec<_>>();
+    winners.sort();
+    winners
   }
 
-  pub fn most_minute_to_sleep_by_one_elf(&self, log: &[Entry]) -> (&str, u32, u32) {
-    let all_ids = self.sleeps.iter().map(|(&id, _), | id).collect::<Vec<_>>();
-    all_ids.iter().map(|&id| {
-      let times = self.most_common_minute_to_sleep(id, log);
-      (id, times.0, times.1)
-    })
-      .max_by(|(_, _, a), (_, _, b)| a.cmp(&b))
-      .unwrap_or(("", 0, 0))
+  /// Every guard and bucket pair sharing the highest count, ordered by the tie-break policy.
+  pub fn most_minute_to_sleep_by_one_elf(&self) -> Vec<(&str, u32, u32)> {
+    let all = self.sleeps.keys()
+      .map(|id| id.as_str())
+      .flat_map(|id| {
+        self.most_common_minute_to_sleep(id).into_iter()
+          .map(move |(minute, count)| (id, minute, count))
+      })
+      .collect::<Vec<_>>();
+    let most = all.iter().map(|&(_, _, count)| count).max().unwrap_or(0);
+    let mut winners = all.into_iter()
+      .filter(|&(_, _, count)| count == most)
+      .collect::<Vec<_>>();
+    winners.sort_by_key(|&(guard, minute, _)| self.tie_break.key(guard, minute));
+    winners
   }
 }
 
-fn calc_minute_range(a: &DateTime<Utc>, b: &DateTime<Utc>) -> Vec<u32> {
+/// Buckets every minute in `[a, b)`, so naps crossing the hour or midnight are counted by their real duration.
+fn calc_minute_range(a: &DateTime<Utc>, b: &DateTime<Utc>, bucketing: Bucketing) -> Vec<u32> {
   let mut sleep_times = vec![];
 
-  for min in a.minute()..b.minute() {
-    sleep_times.push(min);
+  let mut time = *a;
+  while time < *b {
+    if let Some(bucket) = bucketing.bucket(&time) {
+      sleep_times.push(bucket);
+    }
+    time += chrono::Duration::minutes(1);
   }
   sleep_times
 }
@@ -131,8 +248,21 @@ fn parse_awake_info(entry: &str) -> bool {
   entry.starts_with("wakes up")
 }
 
+fn parse_action(entry: &str) -> Action<'_> {
+  let guard = parse_guard(entry);
+  if !guard.is_empty() {
+    Action::BeginShift(guard)
+  } else if parse_awake_info(entry) {
+    Action::WakesUp
+  } else if entry.starts_with("falls asleep") {
+    Action::FallsAsleep
+  } else {
+    Action::Unknown
+  }
+}
+
 fn read_input_file(filename: &str) -> String {
-  let mut file = File::open(&Path::new(filename))
+  let mut file = File::open(Path::new(filename))
     .unwrap_or_else(|e| panic!("File {} read error: {}", filename, e));
   let mut buf = String::new();
   file.read_to_string(&mut buf).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
@@ -144,36 +274,142 @@ fn lines_to_vec(input: &str) -> Vec<&str> {
     .collect::<Vec<&str>>()
 }
 
-fn parse_line(input: &str) -> Entry {
-  let date_time_and_action = input.split("] ")
-    .collect::<Vec<&str>>();
-  let date = date_time_and_action[0]
-    .trim_start_matches('[')
-    .split(|c| c == '-' || c == ' ' || c == ':')
-    .map(|el| el.parse::<u32>().unwrap_or_else(|_| panic!("Number cast error")))
-    .collect::<Vec<u32>>();
+#[cfg(test)]
+fn parse_line(line: usize, input: &str) -> Result<Entry<'_>, ParseError> {
+  parse_line_with(line, input, DEFAULT_FORMATS)
+}
+
+fn parse_line_with<'a>(line: usize, input: &'a str, formats: &[TimestampFormat]) -> Result<Entry<'a>, ParseError> {
+  let error = |message: String| ParseError { line, message };
+  let (date_time, action) = input.trim_start()
+    .strip_prefix('[')
+    .and_then(|rest| rest.split_once("] "))
+    .ok_or_else(|| error(format!("expected \"[timestamp] action\", got {:?}", input)))?;
+
+  if let Action::BeginShift(guard) = parse_action(action) {
+    if guard.trim_start_matches('#').parse::<u32>().is_err() {
+      return Err(error(format!("guard ID {} is not a number", guard)));
+    }
+  }
 
-  Entry::new(date[0] as i32,
-             date[1],
-             date[2],
-             date[3], date[4],
-             date_time_and_action[1])
+  parse_timestamp(date_time, formats)
+    .map(|date_time| Entry::new(line, date_time, action))
+    .map_err(error)
 }
 
-fn multiply_id_with_minute(id: &str, minute: u32) -> u32 {
-  let num = id
-    .trim_start_matches('#')
+fn guard_number(id: &str) -> u32 {
+  id.trim_start_matches('#')
     .parse::<u32>()
-    .unwrap_or_else(|e| panic!("Number cast error {:?}", e));
-  minute * num
+    .unwrap_or_else(|e| panic!("Number cast error {:?}", e))
+}
+
+fn multiply_id_with_minute(id: &str, minute: u32) -> u32 {
+  minute * guard_number(id)
+}
+
+fn arg_value(name: &str) -> Option<String> {
+  env::args()
+    .skip_while(|arg| arg != name)
+    .nth(1)
+}
+
+fn print_answers(sleep_calculator: &SleepTime) {
+  let sleepiest = sleep_calculator.get_sleepiest_elf().first().map(|&(guard, _)| guard);
+  let most_common_minute = sleepiest
+    .and_then(|guard| sleep_calculator.most_common_minute_to_sleep(guard).first().map(|&(minute, _)| (guard, minute)));
+  match most_common_minute {
+    Some((guard, minute)) => println!("Part one: {} & {} => {}", guard, minute, multiply_id_with_minute(guard, minute)),
+    None => println!("Part one: no guard slept in a bucketed minute"),
+  }
+
+  match sleep_calculator.most_minute_to_sleep_by_one_elf().first() {
+    Some(&(guard, minute, _)) => println!("Part two: {} & {} => {}", guard, minute, multiply_id_with_minute(guard, minute)),
+    None => println!("Part two: no guard slept in a bucketed minute"),
+  }
+
+  if let Some(name) = arg_value("--strategy") {
+    let strategy = strategy_by_name(&name).unwrap_or_else(|| panic!("Unknown strategy {}", name));
+    strategy.pick(sleep_calculator).iter().for_each(|pick| {
+      let weekday = pick.weekday.map(|day| format!("{:?} ", day)).unwrap_or_default();
+      let minute = pick.minute.map(|minute| format!(" at {}", minute)).unwrap_or_default();
+      println!("{}: {}{} scored {}{}", strategy.name(), weekday, pick.guard, pick.score, minute);
+    });
+  }
+}
+
+fn stream_input(source: &str, sleep_calculator: SleepTime, formats: Vec<TimestampFormat>) {
+  let window = arg_value("--window")
+    .map(|w| w.parse::<usize>().unwrap_or_else(|e| panic!("Number cast error {:?}", e)))
+    .unwrap_or(STREAM_WINDOW);
+  let follow = env::args().any(|arg| arg == "--follow");
+  let mut log_stream = LogStream::new(window, sleep_calculator).with_formats(formats);
+  let mut sleepiest = String::new();
+  let on_update = |sleeps: &SleepTime| {
+    if let Some(&(guard, minutes)) = sleeps.get_sleepiest_elf().first() {
+      if guard != sleepiest {
+        println!("Sleepiest so far: {} with {} minutes", guard, minutes);
+        sleepiest = guard.to_string();
+      }
+    }
+  };
+
+  if source == "-" {
+    let stdin = io::stdin();
+    ingest(&mut log_stream, stdin.lock(), follow, on_update);
+  } else {
+    let file = File::open(Path::new(source))
+      .unwrap_or_else(|e| panic!("File {} read error: {}", source, e));
+    ingest(&mut log_stream, BufReader::new(file), follow, on_update);
+  }
+
+  log_stream.invalid.iter().for_each(|e| eprintln!("Skipped {}", e));
+  if env::args().any(|arg| arg == "--strict") && !log_stream.anomalies.is_empty() {
+    log_stream.anomalies.iter().for_each(|anomaly| eprintln!("{}", anomaly));
+    panic!("Log validation failed with {} anomalies", log_stream.anomalies.len())
+  }
+  log_stream.anomalies.iter().for_each(|anomaly| eprintln!("Repaired {}", anomaly));
+  if !log_stream.late.is_empty() {
+    eprintln!("Dropped {} entries arriving outside the reorder window, first on line {}",
+              log_stream.late.len(), log_stream.late[0]);
+  }
+  print_answers(&log_stream.sleeps);
 }
 
 fn main() {
+  let bucketing = match arg_value("--bucketing").as_deref() {
+    None | Some("midnight") => Bucketing::MidnightHour,
+    Some("day") => Bucketing::MinuteOfDay,
+    Some("quarter") => Bucketing::QuarterHour,
+    Some(other) => panic!("Unknown bucketing {}", other),
+  };
+
+  let tie_break = match arg_value("--tie-break").as_deref() {
+    None | Some("lowest-id") => TieBreak::LowestId,
+    Some("earliest-minute") => TieBreak::EarliestMinute,
+    Some(other) => panic!("Unknown tie-break {}", other),
+  };
+
+  let mut sleep_calculator = SleepTime::new()
+    .with_bucketing(bucketing)
+    .with_tie_break(tie_break);
+
+  let formats = arg_value("--timestamp-format")
+    .map(|name| vec![TimestampFormat::from_name(&name)])
+    .unwrap_or_else(|| DEFAULT_FORMATS.to_vec());
+
+  if let Some(source) = arg_value("--stream") {
+    stream_input(&source, sleep_calculator, formats);
+    return;
+  }
+
   let data = read_input_file("./input.txt");
   let lines = lines_to_vec(&data);
   let mut parsed_lines = lines.iter()
-    .map(|&el| parse_line(el))
-    .collect::<Vec<Entry>>();
+    .enumerate()
+    .filter(|(_, el)| !el.trim().is_empty())
+    .map(|(idx, &el)| parse_line_with(idx + 1, el, &formats))
+    .collect::<Result<Vec<Entry>, ParseError>>()
+    .unwrap_or_else(|e| panic!("Parse error on {}", e));
 
   parsed_lines.sort_by(|a, b| {
     let duration_a = a.date_time.timestamp();
@@ -181,15 +417,58 @@ fn main() {
     duration_a.cmp(&duration_b)
   });
 
-  let mut sleep_calculator = SleepTime::new();
-  sleep_calculator.process_sleep_times(&parsed_lines);
-  let sleepiest_and_sleeps = sleep_calculator.get_sleepiest_elf();
-  let (most_common_minute, _) = sleep_calculator.most_common_minute_to_sleep(sleepiest_and_sleeps.0, &parsed_lines);
+  let mode = if env::args().any(|arg| arg == "--strict") { Mode::Strict } else { Mode::Lenient };
+  let validated = validate_log(&parsed_lines, mode).unwrap_or_else(|anomalies| {
+    anomalies.iter().for_each(|anomaly| eprintln!("{}", anomaly));
+    panic!("Log validation failed with {} anomalies", anomalies.len())
+  });
+  validated.anomalies.iter().for_each(|anomaly| eprintln!("Repaired {}", anomaly));
+  let parsed_lines = validated.entries;
+
+  if let Some(format) = arg_value("--render") {
+    let shifts = shifts(&parsed_lines);
+    let guard = arg_value("--guard");
+    let rendered = match format.as_str() {
+      "chart" => render_chart(&shifts, guard.as_deref()),
+      "csv" => render_csv(&shifts, guard.as_deref()),
+      "svg" => render_svg(&shifts, guard.as_deref()),
+      other => panic!("Unknown render format {}", other),
+    };
+    print!("{}", rendered);
+    return;
+  }
 
-  println!("Part one: {} & {} => {}", sleepiest_and_sleeps.0, most_common_minute, multiply_id_with_minute(sleepiest_and_sleeps.0, most_common_minute));
+  sleep_calculator.process_sleep_times(&parsed_lines);
+  print_answers(&sleep_calculator);
+}
 
-  let res = sleep_calculator.most_minute_to_sleep_by_one_elf(&parsed_lines);
-  println!("Part two: {} & {} => {}", res.0, res.1, multiply_id_with_minute(res.0, res.1));
+/// The example log from the puzzle text.
+#[cfg(test)]
+const PUZZLE_LOG: [&str; 17] = ["[1518-11-01 00:00] Guard #10 begins shift",
+                                "[1518-11-01 00:05] falls asleep",
+                                "[1518-11-01 00:25] wakes up",
+                                "[1518-11-01 00:30] falls asleep",
+                                "[1518-11-01 00:55] wakes up",
+                                "[1518-11-01 23:58] Guard #99 begins shift",
+                                "[1518-11-02 00:40] falls asleep",
+                                "[1518-11-02 00:50] wakes up",
+                                "[1518-11-03 00:05] Guard #10 begins shift",
+                                "[1518-11-03 00:24] falls asleep",
+                                "[1518-11-03 00:29] wakes up",
+                                "[1518-11-04 00:02] Guard #99 begins shift",
+                                "[1518-11-04 00:36] falls asleep",
+                                "[1518-11-04 00:46] wakes up",
+                                "[1518-11-05 00:03] Guard #99 begins shift",
+                                "[1518-11-05 00:45] falls asleep",
+                                "[1518-11-05 00:55] wakes up"];
+
+/// Parses test lines, numbering them from 1.
+#[cfg(test)]
+fn test_log(lines: &[&'static str]) -> Vec<Entry<'static>> {
+  lines.iter()
+    .enumerate()
+    .map(|(idx, &el)| parse_line(idx + 1, el).unwrap())
+    .collect::<Vec<Entry>>()
 }
 
 #[test]
@@ -213,7 +492,8 @@ fn first_test() {
                    "[1518-11-05 00:55] wakes up"];
 
   let mut parsed_lines = lines.iter()
-    .map(|&el| parse_line(el))
+    .enumerate()
+    .map(|(idx, &el)| parse_line(idx + 1, el).unwrap())
     .collect::<Vec<Entry>>();
   parsed_lines.sort_by(|a, b| {
     let duration_a = a.date_time.timestamp();
@@ -226,10 +506,9 @@ fn first_test() {
 
   let sleepiest = sleep_calculator.get_sleepiest_elf();
 
-  assert_eq!(sleepiest.0, "#10");
-  assert_eq!(sleepiest.1, 50);
-  let times = sleep_calculator.most_common_minute_to_sleep("#10", &parsed_lines);
-  assert_eq!(times.0, 24);
+  assert_eq!(sleepiest, vec![("#10", 50)]);
+  let times = sleep_calculator.most_common_minute_to_sleep("#10");
+  assert_eq!(times, vec![(24, 2)]);
 }
 
 #[test]
@@ -253,7 +532,8 @@ fn second_test() {
                    "[1518-11-05 00:55] wakes up"];
 
   let mut parsed_lines = lines.iter()
-    .map(|&el| parse_line(el))
+    .enumerate()
+    .map(|(idx, &el)| parse_line(idx + 1, el).unwrap())
     .collect::<Vec<Entry>>();
   parsed_lines.sort_by(|a, b| {
     let duration_a = a.date_time.timestamp();
@@ -264,7 +544,93 @@ fn second_test() {
   let mut sleep_calculator = SleepTime::new();
   sleep_calculator.process_sleep_times(&parsed_lines);
 
-  let res = sleep_calculator.most_minute_to_sleep_by_one_elf(&parsed_lines);
-  assert_eq!(res.0, "#99");
-  assert_eq!(res.1, 45);
+  let res = sleep_calculator.most_minute_to_sleep_by_one_elf();
+  assert_eq!(res, vec![("#99", 45, 3)]);
+}
+
+#[test]
+fn crossing_hour_and_midnight_test() {
+  let lines = ["[1518-11-01 23:50] Guard #10 begins shift",
+                   "[1518-11-01 23:58] falls asleep",
+                   "[1518-11-02 00:12] wakes up",
+                   "[1518-11-02 00:50] falls asleep",
+                   "[1518-11-02 01:05] wakes up"];
+  let parsed_lines = test_log(&lines);
+
+  let mut sleep_calculator = SleepTime::new();
+  sleep_calculator.process_sleep_times(&parsed_lines);
+  assert_eq!(sleep_calculator.get_sleepiest_elf(), vec![("#10", 22)]);
+  let mut whole_day = SleepTime::new().with_bucketing(Bucketing::MinuteOfDay);
+  whole_day.process_sleep_times(&parsed_lines);
+  assert_eq!(whole_day.get_sleepiest_elf(), vec![("#10", 29)]);
+  let midnight = calc_minute_range(&parsed_lines[1].date_time, &parsed_lines[2].date_time, Bucketing::MidnightHour);
+  assert_eq!(midnight, (0..12).collect::<Vec<u32>>());
+  let late = calc_minute_range(&parsed_lines[3].date_time, &parsed_lines[4].date_time, Bucketing::MidnightHour);
+  assert_eq!(late, (50..60).collect::<Vec<u32>>());
+
+  let day = calc_minute_range(&parsed_lines[1].date_time, &parsed_lines[2].date_time, Bucketing::MinuteOfDay);
+  assert_eq!(&day[..3], &[1438, 1439, 0]);
+  assert_eq!(day.len(), 14);
+
+  let mut quarters = SleepTime::new().with_bucketing(Bucketing::QuarterHour);
+  quarters.process_sleep_times(&parsed_lines);
+  assert_eq!(quarters.most_common_minute_to_sleep("#10"), vec![(0, 12)]);
+}
+
+#[test]
+fn naps_outside_midnight_hour_test() {
+  use strategy::Strategy;
+  let lines = ["[1518-11-01 23:00] Guard #10 begins shift",
+               "[1518-11-01 23:10] falls asleep",
+               "[1518-11-01 23:50] wakes up",
+               "[1518-11-02 00:00] Guard #99 begins shift",
+               "[1518-11-02 00:10] falls asleep",
+               "[1518-11-02 00:15] wakes up"];
+  let parsed_lines = test_log(&lines);
+
+  let mut sleep_calculator = SleepTime::new();
+  sleep_calculator.process_sleep_times(&parsed_lines);
+  assert_eq!(sleep_calculator.get_sleepiest_elf(), vec![("#99", 5)]);
+  assert_eq!(sleep_calculator.most_common_minute_to_sleep("#10"), vec![]);
+  assert_eq!(strategy::MostMinutesAsleep.pick(&sleep_calculator)[0].minute, Some(10));
+}
+
+#[test]
+fn tie_break_test() {
+  let lines = ["[1518-11-01 00:00] Guard #99 begins shift",
+               "[1518-11-01 00:20] falls asleep",
+               "[1518-11-01 00:22] wakes up",
+               "[1518-11-02 00:00] Guard #10 begins shift",
+               "[1518-11-02 00:30] falls asleep",
+               "[1518-11-02 00:31] wakes up",
+               "[1518-11-02 00:40] falls asleep",
+               "[1518-11-02 00:41] wakes up"];
+  let parsed_lines = test_log(&lines);
+
+  let mut by_id = SleepTime::new();
+  by_id.process_sleep_times(&parsed_lines);
+  assert_eq!(by_id.get_sleepiest_elf(), vec![("#10", 2), ("#99", 2)]);
+  assert_eq!(by_id.most_common_minute_to_sleep("#10"), vec![(30, 1), (40, 1)]);
+  assert_eq!(by_id.most_minute_to_sleep_by_one_elf(),
+             vec![("#10", 30, 1), ("#10", 40, 1), ("#99", 20, 1), ("#99", 21, 1)]);
+
+  let mut by_minute = SleepTime::new().with_tie_break(TieBreak::EarliestMinute);
+  by_minute.process_sleep_times(&parsed_lines);
+  assert_eq!(by_minute.most_minute_to_sleep_by_one_elf(),
+             vec![("#99", 20, 1), ("#99", 21, 1), ("#10", 30, 1), ("#10", 40, 1)]);
+}
+
+#[test]
+fn parse_error_test() {
+  let entry = parse_line(3, "[1518-11-01T02:05:00+02:00] falls asleep").unwrap();
+  assert_eq!(entry.date_time, Utc.with_ymd_and_hms(1518, 11, 1, 0, 5, 0).unwrap());
+  assert_eq!(entry.action, "falls asleep");
+
+  let invalid_date = parse_line(7, "[1518-02-30 00:05] falls asleep").unwrap_err();
+  assert_eq!(invalid_date.line, 7);
+  assert!(invalid_date.to_string().starts_with("line 7: 1518-02-30 00:05"));
+  assert_eq!(parse_line(8, "falls asleep").unwrap_err().line, 8);
+  assert!(parse_line_with(9, "[1518-11-01 00:05] falls asleep", &[TimestampFormat::Iso8601]).is_err());
+  assert_eq!(parse_line(10, "[1518-11-01 00:00] Guard #abc begins shift").unwrap_err().to_string(),
+             "line 10: guard ID #abc is not a number");
 }
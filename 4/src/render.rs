@@ -0,0 +1,152 @@
+use chrono::prelude::*;
+use {parse_action, Action, Entry};
+
+const MINUTES: usize = 60;
+const SVG_MINUTE_WIDTH: usize = 10;
+const SVG_ROW_HEIGHT: usize = 16;
+const SVG_LABEL_WIDTH: usize = 110;
+
+#[derive(Debug, PartialEq)]
+pub struct Shift<'a> {
+  /// Day of the midnight hour the shift covers, so a shift beginning at 23:58 belongs to the next day.
+  pub date: NaiveDate,
+  pub guard: &'a str,
+  pub asleep: Vec<bool>,
+}
+
+/// Splits a time-sorted log into shifts and marks the minutes of the midnight hour spent asleep.
+pub fn shifts<'a>(log: &[Entry<'a>]) -> Vec<Shift<'a>> {
+  let mut shifts: Vec<Shift> = vec![];
+  let mut bed_time = None;
+  for entry in log.iter() {
+    match parse_action(entry.action) {
+      Action::BeginShift(guard) => {
+        let date = entry.date_time.date_naive();
+        let date = if entry.date_time.hour() >= 12 { date.succ_opt().unwrap_or(date) } else { date };
+        shifts.push(Shift { date, guard, asleep: vec![false; MINUTES] });
+        bed_time = None;
+      }
+      Action::FallsAsleep => bed_time = Some(entry.date_time),
+      Action::WakesUp => {
+        if let (Some(start), Some(shift)) = (bed_time.take(), shifts.last_mut()) {
+          let mut time = start;
+          while time < entry.date_time {
+            if time.hour() == 0 && time.date_naive() == shift.date {
+              shift.asleep[time.minute() as usize] = true;
+            }
+            time += chrono::Duration::minutes(1);
+          }
+        }
+      }
+      Action::Unknown => (),
+    }
+  }
+  shifts
+}
+
+fn filtered<'a, 'b>(shifts: &'b [Shift<'a>], guard: Option<&'b str>) -> impl Iterator<Item=&'b Shift<'a>> {
+  shifts.iter().filter(move |shift| guard.is_none_or(|id| shift.guard == id))
+}
+
+/// Renders the puzzle's `Date ID Minute` chart with `#` for asleep and `.` for awake.
+pub fn render_chart(shifts: &[Shift], guard: Option<&str>) -> String {
+  let id_width = filtered(shifts, guard).map(|shift| shift.guard.len()).max().unwrap_or(0).max(2);
+  let indent = " ".repeat(5 + 2 + id_width + 2);
+  let tens = (0..MINUTES).map(|min| (min / 10).to_string()).collect::<String>();
+  let ones = (0..MINUTES).map(|min| (min % 10).to_string()).collect::<String>();
+
+  let mut out = format!("{:<5}  {:<w$}  Minute\n{}{}\n{}{}\n", "Date", "ID", indent, tens, indent, ones, w = id_width);
+  filtered(shifts, guard).for_each(|shift| {
+    let minutes = shift.asleep.iter().map(|&asleep| if asleep { '#' } else { '.' }).collect::<String>();
+    out.push_str(&format!("{}  {:<w$}  {}\n", shift.date.format("%m-%d"), shift.guard, minutes, w = id_width));
+  });
+  out
+}
+
+/// One row per shift with a 0/1 column for each minute of the midnight hour.
+pub fn render_csv(shifts: &[Shift], guard: Option<&str>) -> String {
+  let header = (0..MINUTES).map(|min| format!(",{:02}", min)).collect::<String>();
+  let mut out = format!("date,guard{}\n", header);
+  filtered(shifts, guard).for_each(|shift| {
+    let minutes = shift.asleep.iter().map(|&asleep| if asleep { ",1" } else { ",0" }).collect::<String>();
+    out.push_str(&format!("{},{}{}\n", shift.date.format("%Y-%m-%d"), shift.guard, minutes));
+  });
+  out
+}
+
+/// Gantt chart with one row per shift and one bar per nap.
+pub fn render_svg(shifts: &[Shift], guard: Option<&str>) -> String {
+  let rows = filtered(shifts, guard).collect::<Vec<_>>();
+  let width = SVG_LABEL_WIDTH + MINUTES * SVG_MINUTE_WIDTH;
+  let height = (rows.len() + 1) * SVG_ROW_HEIGHT;
+
+  let mut out = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"12\">\n",
+                        width, height);
+  for min in (0..MINUTES).step_by(10) {
+    let x = SVG_LABEL_WIDTH + min * SVG_MINUTE_WIDTH;
+    out.push_str(&format!("  <line x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"{}\" stroke=\"#ccc\"/>\n", x, x, height));
+    out.push_str(&format!("  <text x=\"{}\" y=\"12\">{:02}</text>\n", x + 2, min));
+  }
+  rows.iter().enumerate().for_each(|(idx, shift)| {
+    let y = (idx + 1) * SVG_ROW_HEIGHT;
+    out.push_str(&format!("  <text x=\"0\" y=\"{}\">{} {}</text>\n", y + 12, shift.date.format("%m-%d"), shift.guard));
+    nap_ranges(&shift.asleep).iter().for_each(|&(start, end)| {
+      out.push_str(&format!("  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#4a6fa5\"><title>{:02}-{:02}</title></rect>\n",
+                            SVG_LABEL_WIDTH + start * SVG_MINUTE_WIDTH, y + 2,
+                            (end - start) * SVG_MINUTE_WIDTH, SVG_ROW_HEIGHT - 4,
+                            start, end));
+    });
+  });
+  out.push_str("</svg>\n");
+  out
+}
+
+fn nap_ranges(asleep: &[bool]) -> Vec<(usize, usize)> {
+  let mut ranges = vec![];
+  let mut start = None;
+  for (min, &is_asleep) in asleep.iter().chain([false].iter()).enumerate() {
+    match (start, is_asleep) {
+      (None, true) => start = Some(min),
+      (Some(from), false) => {
+        ranges.push((from, min));
+        start = None;
+      }
+      _ => (),
+    }
+  }
+  ranges
+}
+
+#[test]
+fn chart_test() {
+  let log = ::test_log(&::PUZZLE_LOG);
+  let chart = render_chart(&shifts(&log), None);
+  assert_eq!(chart, "\
+Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########.....
+");
+  let only_10 = render_chart(&shifts(&log), Some("#10"));
+  assert_eq!(only_10.lines().count(), 5);
+}
+
+#[test]
+fn csv_and_svg_test() {
+  let log = ::test_log(&::PUZZLE_LOG);
+  let shifts = shifts(&log);
+  let csv = render_csv(&shifts, Some("#99"));
+  let lines = csv.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 4);
+  assert!(lines[0].starts_with("date,guard,00,01,"));
+  assert!(lines[1].starts_with("1518-11-02,#99,0,0,"));
+  assert_eq!(lines[1].matches(",1").count(), 10);
+
+  let svg = render_svg(&shifts, None);
+  assert_eq!(svg.matches("<rect").count(), 6);
+  assert!(svg.contains("<title>05-25</title>"));
+}
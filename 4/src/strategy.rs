@@ -0,0 +1,244 @@
+use chrono::prelude::*;
+use std::collections::HashMap;
+use {Nap, SleepTime};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pick<'a> {
+  pub guard: &'a str,
+  pub score: f64,
+  pub minute: Option<u32>,
+  pub weekday: Option<Weekday>,
+}
+
+impl<'a> Pick<'a> {
+  fn new(guard: &'a str, score: f64) -> Self {
+    Pick { guard, score, minute: None, weekday: None }
+  }
+}
+
+pub trait Strategy {
+  fn name(&self) -> &'static str;
+
+  /// Returns every tied winner, ordered by the tie-break policy of `sleeps`.
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>>;
+}
+
+/// Puzzle strategy 1: the guard with the most minutes asleep, and their most common minute.
+pub struct MostMinutesAsleep;
+
+/// Puzzle strategy 2: the guard most frequently asleep on the same minute.
+pub struct MostFrequentMinute;
+
+pub struct MostSessions;
+
+pub struct LongestNap;
+
+/// The guard whose nap lengths have the lowest variance. Guards with fewer than two naps are skipped.
+pub struct MostConsistent;
+
+/// The guard with the most minutes asleep for every weekday on which anybody slept.
+pub struct SleepiestPerWeekday;
+
+impl Strategy for MostMinutesAsleep {
+  fn name(&self) -> &'static str {
+    "most-minutes"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let scores = sleeps.sleeps.iter()
+      .map(|(guard, &minutes)| (guard.as_str(), f64::from(minutes)))
+      .collect::<Vec<_>>();
+    let picks = best_of(scores, false).into_iter()
+      .flat_map(|(guard, score)| {
+        sleeps.most_common_minute_to_sleep(guard).into_iter()
+          .map(move |(minute, _)| Pick { minute: Some(minute), ..Pick::new(guard, score) })
+      })
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+impl Strategy for MostFrequentMinute {
+  fn name(&self) -> &'static str {
+    "most-frequent-minute"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let picks = sleeps.most_minute_to_sleep_by_one_elf().into_iter()
+      .map(|(guard, minute, count)| Pick { minute: Some(minute), ..Pick::new(guard, f64::from(count)) })
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+impl Strategy for MostSessions {
+  fn name(&self) -> &'static str {
+    "most-sessions"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let scores = naps_by_guard(&sleeps.naps).into_iter()
+      .map(|(guard, naps)| (guard, naps.len() as f64))
+      .collect::<Vec<_>>();
+    let picks = best_of(scores, false).into_iter()
+      .map(|(guard, score)| Pick::new(guard, score))
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+impl Strategy for LongestNap {
+  fn name(&self) -> &'static str {
+    "longest-nap"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let scores = sleeps.naps.iter()
+      .map(|nap| (nap.guard.as_str(), f64::from(nap.minutes())))
+      .collect::<Vec<_>>();
+    let picks = best_of(scores, false).into_iter()
+      .map(|(guard, score)| Pick::new(guard, score))
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+impl Strategy for MostConsistent {
+  fn name(&self) -> &'static str {
+    "most-consistent"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let scores = naps_by_guard(&sleeps.naps).into_iter()
+      .filter(|(_, naps)| naps.len() > 1)
+      .map(|(guard, naps)| {
+        let lengths = naps.iter().map(|nap| f64::from(nap.minutes())).collect::<Vec<_>>();
+        (guard, variance(&lengths))
+      })
+      .collect::<Vec<_>>();
+    let picks = best_of(scores, true).into_iter()
+      .map(|(guard, score)| Pick::new(guard, score))
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+impl Strategy for SleepiestPerWeekday {
+  fn name(&self) -> &'static str {
+    "weekday"
+  }
+
+  fn pick<'a>(&self, sleeps: &'a SleepTime) -> Vec<Pick<'a>> {
+    let mut minutes: HashMap<(u32, &str), u32> = HashMap::new();
+    sleeps.naps.iter().for_each(|nap| {
+      *minutes.entry((nap.start.weekday().num_days_from_monday(), nap.guard.as_str())).or_insert(0) += nap.minutes();
+    });
+    let mut weekdays = minutes.keys().map(|&(day, _)| day).collect::<Vec<_>>();
+    weekdays.sort();
+    weekdays.dedup();
+
+    let picks = weekdays.into_iter()
+      .flat_map(|day| {
+        let scores = minutes.iter()
+          .filter(|(&(d, _), _)| d == day)
+          .map(|(&(_, guard), &total)| (guard, f64::from(total)))
+          .collect::<Vec<_>>();
+        best_of(scores, false).into_iter()
+          .map(move |(guard, score)| Pick { weekday: Some(weekday_from_monday(day)), ..Pick::new(guard, score) })
+      })
+      .collect();
+    ordered(sleeps, picks)
+  }
+}
+
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn Strategy>> {
+  match name {
+    "most-minutes" => Some(Box::new(MostMinutesAsleep)),
+    "most-frequent-minute" => Some(Box::new(MostFrequentMinute)),
+    "most-sessions" => Some(Box::new(MostSessions)),
+    "longest-nap" => Some(Box::new(LongestNap)),
+    "most-consistent" => Some(Box::new(MostConsistent)),
+    "weekday" => Some(Box::new(SleepiestPerWeekday)),
+    _ => None,
+  }
+}
+
+fn naps_by_guard(naps: &[Nap]) -> HashMap<&str, Vec<&Nap>> {
+  let mut by_guard: HashMap<&str, Vec<&Nap>> = HashMap::new();
+  naps.iter().for_each(|nap| by_guard.entry(nap.guard.as_str()).or_default().push(nap));
+  by_guard
+}
+
+fn variance(values: &[f64]) -> f64 {
+  let mean = values.iter().sum::<f64>() / values.len() as f64;
+  values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64
+}
+
+fn weekday_from_monday(day: u32) -> Weekday {
+  (0..day).fold(Weekday::Mon, |weekday, _| weekday.succ())
+}
+
+fn ordered<'a>(sleeps: &SleepTime, mut picks: Vec<Pick<'a>>) -> Vec<Pick<'a>> {
+  picks.sort_by_key(|pick| {
+    (pick.weekday.map(|day| day.num_days_from_monday()), sleeps.tie_break.key(pick.guard, pick.minute.unwrap_or(0)))
+  });
+  picks
+}
+
+/// Keeps the best score of every guard, then every guard sharing the overall best score.
+fn best_of(scores: Vec<(&str, f64)>, lowest_wins: bool) -> Vec<(&str, f64)> {
+  let better = |a: f64, b: f64| if lowest_wins { a < b } else { a > b };
+  let mut per_guard: HashMap<&str, f64> = HashMap::new();
+  scores.into_iter().for_each(|(guard, score)| {
+    per_guard.entry(guard)
+      .and_modify(|best| if better(score, *best) { *best = score })
+      .or_insert(score);
+  });
+
+  let best = per_guard.values().cloned().fold(None, |acc: Option<f64>, score| match acc {
+    Some(b) if !better(score, b) => Some(b),
+    _ => Some(score),
+  });
+  per_guard.into_iter()
+    .filter(|&(_, score)| Some(score) == best)
+    .collect::<Vec<_>>()
+}
+
+#[test]
+fn puzzle_strategies_test() {
+  let log = ::test_log(&::PUZZLE_LOG);
+  let mut sleeps = SleepTime::new();
+  sleeps.process_sleep_times(&log);
+
+  let one = MostMinutesAsleep.pick(&sleeps);
+  assert_eq!(one, vec![Pick { minute: Some(24), ..Pick::new("#10", 50.0) }]);
+  let two = MostFrequentMinute.pick(&sleeps);
+  assert_eq!(two, vec![Pick { minute: Some(45), ..Pick::new("#99", 3.0) }]);
+  assert_eq!(LongestNap.pick(&sleeps), vec![Pick::new("#10", 25.0)]);
+  assert_eq!(MostConsistent.pick(&sleeps), vec![Pick::new("#99", 0.0)]);
+}
+
+#[test]
+fn ties_test() {
+  let log = ::test_log(&["[1518-11-01 00:00] Guard #99 begins shift",
+                            "[1518-11-01 00:05] falls asleep",
+                            "[1518-11-01 00:15] wakes up",
+                            "[1518-11-02 00:00] Guard #10 begins shift",
+                            "[1518-11-02 00:20] falls asleep",
+                            "[1518-11-02 00:30] wakes up",
+                            "[1518-11-03 00:00] Guard #7 begins shift",
+                            "[1518-11-03 00:20] falls asleep",
+                            "[1518-11-03 00:25] wakes up"]);
+  let mut sleeps = SleepTime::new();
+  sleeps.process_sleep_times(&log);
+
+  let guards = |picks: Vec<Pick>| picks.iter().map(|p| p.guard.to_string()).collect::<Vec<_>>();
+  assert_eq!(guards(MostSessions.pick(&sleeps)), vec!["#7", "#10", "#99"]);
+  assert_eq!(guards(LongestNap.pick(&sleeps)), vec!["#10", "#99"]);
+  let weekdays = SleepiestPerWeekday.pick(&sleeps).iter()
+    .map(|p| (p.weekday.unwrap(), p.guard))
+    .collect::<Vec<_>>();
+  assert_eq!(weekdays, vec![(Weekday::Fri, "#99"), (Weekday::Sat, "#10"), (Weekday::Sun, "#7")]);
+  assert!(strategy_by_name("weekday").is_some());
+  assert!(strategy_by_name("unknown").is_none());
+}
@@ -0,0 +1,241 @@
+use chrono::prelude::*;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+use std::{thread, time};
+use timestamp::{TimestampFormat, DEFAULT_FORMATS};
+use validate::{Anomaly, AnomalyKind, ShiftState};
+use {parse_action, parse_line_with, Entry, ParseError, SleepTime};
+
+const FOLLOW_POLL_MILLIS: u64 = 500;
+const FOLLOW_FLUSH_MILLIS: u64 = 2000;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Pending {
+  date_time: DateTime<Utc>,
+  seq: usize,
+  action: String,
+}
+
+impl Ord for Pending {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.date_time, self.seq).cmp(&(other.date_time, other.seq))
+  }
+}
+
+impl PartialOrd for Pending {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+/// Reorders incoming lines within a bounded window and feeds them to `SleepTime` one at a time.
+/// A line arriving after a later entry has already left the window cannot be placed and is dropped.
+/// Emitted entries go through the same shift state machine as `validate_log` in lenient mode.
+/// Unlike there, a sleep cut short by a shift change has already been folded in, which is
+/// harmless because `SleepTime` only counts a nap once the guard wakes up.
+pub struct LogStream {
+  window: usize,
+  buffer: BinaryHeap<Reverse<Pending>>,
+  seq: usize,
+  last_emitted: Option<DateTime<Utc>>,
+  formats: Vec<TimestampFormat>,
+  state: ShiftState,
+  last_kept: Option<(usize, DateTime<Utc>)>,
+  pub late: Vec<usize>,
+  pub anomalies: Vec<Anomaly>,
+  pub invalid: Vec<ParseError>,
+  pub sleeps: SleepTime,
+}
+
+impl LogStream {
+  pub fn new(window: usize, sleeps: SleepTime) -> Self {
+    LogStream {
+      window: window.max(1),
+      buffer: BinaryHeap::new(),
+      seq: 0,
+      last_emitted: None,
+      formats: DEFAULT_FORMATS.to_vec(),
+      state: ShiftState::default(),
+      last_kept: None,
+      late: vec![],
+      anomalies: vec![],
+      invalid: vec![],
+      sleeps,
+    }
+  }
+
+  pub fn with_formats(mut self, formats: Vec<TimestampFormat>) -> Self {
+    self.formats = formats;
+    self
+  }
+
+  /// Buffers one raw log line, returning how many entries were emitted to the statistics.
+  pub fn push_line(&mut self, text: &str) -> usize {
+    let text = text.trim_end();
+    self.seq += 1;
+    if text.is_empty() {
+      return 0;
+    }
+    match parse_line_with(self.seq, text, &self.formats) {
+      Ok(entry) => self.buffer.push(Reverse(Pending { date_time: entry.date_time, seq: self.seq, action: entry.action.to_string() })),
+      Err(e) => {
+        self.invalid.push(e);
+        return 0;
+      }
+    }
+
+    let mut emitted = 0;
+    while self.buffer.len() > self.window {
+      emitted += self.emit_one();
+    }
+    emitted
+  }
+
+  /// Drains the reorder window, e.g. at the end of the input.
+  pub fn flush(&mut self) -> usize {
+    let mut emitted = 0;
+    while !self.buffer.is_empty() {
+      emitted += self.emit_one();
+    }
+    emitted
+  }
+
+  /// Called while following an input that has gone quiet. After `FOLLOW_FLUSH_MILLIS` without new
+  /// lines the window is drained so the statistics catch up. Lines arriving afterwards with older
+  /// timestamps are then dropped as late.
+  pub fn idle(&mut self, quiet_for: time::Duration) -> usize {
+    if quiet_for >= time::Duration::from_millis(FOLLOW_FLUSH_MILLIS) {
+      self.flush()
+    } else {
+      0
+    }
+  }
+
+  /// Drains the window at the end of the input and reports a guard still asleep.
+  pub fn finish(&mut self) -> usize {
+    let emitted = self.flush();
+    if let (true, Some((line, date_time))) = (self.state.is_asleep(), self.last_kept) {
+      self.anomalies.push(Anomaly { line, date_time, kind: AnomalyKind::AsleepAtEndOfLog });
+    }
+    emitted
+  }
+
+  fn emit_one(&mut self) -> usize {
+    match self.buffer.pop() {
+      Some(Reverse(pending)) => {
+        if self.last_emitted.is_some_and(|last| pending.date_time < last) {
+          self.late.push(pending.seq);
+          return 0;
+        }
+        self.last_emitted = Some(pending.date_time);
+        let entry = Entry::new(pending.seq, pending.date_time, &pending.action);
+        let (anomaly, keep) = self.state.advance(parse_action(entry.action));
+        if let Some(kind) = anomaly {
+          self.anomalies.push(Anomaly { line: entry.line, date_time: entry.date_time, kind });
+        }
+        if keep {
+          self.last_kept = Some((entry.line, entry.date_time));
+          self.sleeps.push_entry(&entry);
+        }
+        1
+      }
+      None => 0,
+    }
+  }
+}
+
+/// Reads lines until the end of input, or forever when following a growing file. `on_update` is
+/// called with the current statistics whenever new entries have been folded in.
+pub fn ingest<R: BufRead, F: FnMut(&SleepTime)>(stream: &mut LogStream, mut reader: R, follow: bool, mut on_update: F) {
+  let mut partial = String::new();
+  let mut last_read = time::Instant::now();
+  loop {
+    let read = reader.read_line(&mut partial).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
+    if read == 0 {
+      if follow {
+        if stream.idle(last_read.elapsed()) > 0 {
+          on_update(&stream.sleeps);
+        }
+        thread::sleep(time::Duration::from_millis(FOLLOW_POLL_MILLIS));
+        continue;
+      }
+      if stream.push_line(&partial) + stream.finish() > 0 {
+        on_update(&stream.sleeps);
+      }
+      return;
+    }
+    last_read = time::Instant::now();
+    if partial.ends_with('\n') {
+      if stream.push_line(&partial) > 0 {
+        on_update(&stream.sleeps);
+      }
+      partial.clear();
+    }
+  }
+}
+
+#[test]
+fn reorder_window_test() {
+  let lines = ["[1518-11-01 00:05] falls asleep",
+               "[1518-11-01 00:00] Guard #10 begins shift",
+               "[1518-11-01 00:25] wakes up",
+               "[1518-11-02 00:40] falls asleep",
+               "[1518-11-01 23:58] Guard #99 begins shift",
+               "[1518-11-02 00:50] wakes up"];
+  let mut stream = LogStream::new(2, SleepTime::new());
+  ingest(&mut stream, lines.join("\n").as_bytes(), false, |_| ());
+
+  assert!(stream.late.is_empty());
+  assert_eq!(stream.sleeps.get_sleepiest_elf(), vec![("#10", 20)]);
+  assert_eq!(stream.sleeps.most_minute_to_sleep_by_one_elf().len(), 30);
+}
+
+#[test]
+fn late_entry_test() {
+  let mut stream = LogStream::new(1, SleepTime::new());
+  let mut updates = vec![];
+  let input = "[1518-11-01 00:00] Guard #10 begins shift\n\
+               [1518-11-01 00:05] falls asleep\n\
+               [1518-11-01 00:25] wakes up\n\
+               [1518-11-01 00:01] falls asleep\n\
+               [1518-02-30 00:01] falls asleep\n";
+  ingest(&mut stream, input.as_bytes(), false, |sleeps| updates.push(sleeps.naps.len()));
+
+  assert_eq!(stream.late, vec![4]);
+  assert_eq!(stream.invalid.iter().map(|e| e.line).collect::<Vec<_>>(), vec![5]);
+  assert_eq!(updates, vec![0, 0, 1]);
+  assert_eq!(stream.sleeps.get_sleepiest_elf(), vec![("#10", 20)]);
+}
+
+#[test]
+fn idle_flush_test() {
+  let mut stream = LogStream::new(64, SleepTime::new());
+  ::PUZZLE_LOG.iter().for_each(|line| { stream.push_line(line); });
+  assert!(stream.sleeps.get_sleepiest_elf().is_empty());
+
+  assert_eq!(stream.idle(time::Duration::from_millis(FOLLOW_POLL_MILLIS)), 0);
+  assert_eq!(stream.idle(time::Duration::from_millis(FOLLOW_FLUSH_MILLIS)), 17);
+  assert_eq!(stream.sleeps.get_sleepiest_elf(), vec![("#10", 50)]);
+  stream.push_line("[1518-11-05 00:50] falls asleep");
+  assert_eq!(stream.flush(), 0);
+  assert_eq!(stream.late, vec![18]);
+}
+
+#[test]
+fn stream_anomalies_test() {
+  let mut stream = LogStream::new(4, SleepTime::new());
+  let input = "[1518-11-01 00:01] falls asleep\n\
+               [1518-11-01 00:02] Guard #10 begins shift\n\
+               [1518-11-01 00:03] wakes up\n\
+               [1518-11-01 00:05] falls asleep\n\
+               [1518-11-01 00:25] wakes up\n\
+               [1518-11-01 00:30] falls asleep\n";
+  ingest(&mut stream, input.as_bytes(), false, |_| ());
+
+  let kinds = stream.anomalies.iter().map(|a| (a.line, a.kind)).collect::<Vec<_>>();
+  assert_eq!(kinds, vec![(1, AnomalyKind::SleepBeforeShift),
+                         (3, AnomalyKind::WakeWithoutSleep),
+                         (6, AnomalyKind::AsleepAtEndOfLog)]);
+  assert_eq!(stream.sleeps.get_sleepiest_elf(), vec![("#10", 20)]);
+}
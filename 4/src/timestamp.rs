@@ -0,0 +1,85 @@
+use chrono::prelude::*;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TimestampFormat {
+  /// `1518-11-01 00:05`, as in the puzzle input.
+  Puzzle,
+  /// `1518-11-01 00:05:30`
+  WithSeconds,
+  /// `1518-11-01T00:05:30+02:00`, `...Z` or without an offset. Offsets are converted to UTC.
+  Iso8601,
+  /// A chrono format string. When it contains an offset (`%z`, `%:z`) the time is converted to UTC.
+  Custom(String),
+}
+
+pub const DEFAULT_FORMATS: &[TimestampFormat] = &[
+  TimestampFormat::Puzzle,
+  TimestampFormat::WithSeconds,
+  TimestampFormat::Iso8601,
+];
+
+impl TimestampFormat {
+  pub fn from_name(name: &str) -> Self {
+    match name {
+      "puzzle" => TimestampFormat::Puzzle,
+      "seconds" => TimestampFormat::WithSeconds,
+      "iso8601" => TimestampFormat::Iso8601,
+      custom => TimestampFormat::Custom(custom.to_string()),
+    }
+  }
+
+  pub fn parse(&self, input: &str) -> Result<DateTime<Utc>, String> {
+    match self {
+      TimestampFormat::Puzzle => parse_naive(input, "%Y-%m-%d %H:%M"),
+      TimestampFormat::WithSeconds => parse_naive(input, "%Y-%m-%d %H:%M:%S"),
+      TimestampFormat::Iso8601 => DateTime::parse_from_rfc3339(input)
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .or_else(|_| parse_naive(input, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| parse_naive(input, "%Y-%m-%dT%H:%M")),
+      TimestampFormat::Custom(format) => DateTime::parse_from_str(input, format)
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .or_else(|_| parse_naive(input, format)),
+    }
+  }
+}
+
+fn parse_naive(input: &str, format: &str) -> Result<DateTime<Utc>, String> {
+  NaiveDateTime::parse_from_str(input, format)
+    .map(|date_time| date_time.and_utc())
+    .map_err(|e| format!("{} does not match {}: {}", input, format, e))
+}
+
+/// Tries each format in turn and reports the first format's error when none match.
+pub fn parse_timestamp(input: &str, formats: &[TimestampFormat]) -> Result<DateTime<Utc>, String> {
+  let mut first_error = None;
+  for format in formats.iter() {
+    match format.parse(input) {
+      Ok(date_time) => return Ok(date_time),
+      Err(e) => { first_error.get_or_insert(e); }
+    }
+  }
+  Err(first_error.unwrap_or_else(|| "no timestamp format configured".to_string()))
+}
+
+#[test]
+fn formats_test() {
+  let expected = Utc.with_ymd_and_hms(1518, 11, 1, 0, 5, 0).unwrap();
+  assert_eq!(parse_timestamp("1518-11-01 00:05", DEFAULT_FORMATS), Ok(expected));
+  assert_eq!(parse_timestamp("1518-11-01 00:05:00", DEFAULT_FORMATS), Ok(expected));
+  assert_eq!(parse_timestamp("1518-11-01T00:05:00Z", DEFAULT_FORMATS), Ok(expected));
+  assert_eq!(parse_timestamp("1518-11-01T02:05:00+02:00", DEFAULT_FORMATS), Ok(expected));
+  assert_eq!(parse_timestamp("1518-10-31T23:05", &[TimestampFormat::Iso8601]),
+             Ok(Utc.with_ymd_and_hms(1518, 10, 31, 23, 5, 0).unwrap()));
+
+  let custom = TimestampFormat::from_name("%d/%m/%Y %H.%M %z");
+  assert_eq!(custom.parse("01/11/1518 01.05 +0100"), Ok(expected));
+  assert_eq!(TimestampFormat::from_name("%d/%m/%Y %H.%M").parse("01/11/1518 00.05"), Ok(expected));
+}
+
+#[test]
+fn invalid_timestamp_test() {
+  assert!(parse_timestamp("1518-02-30 00:05", DEFAULT_FORMATS).is_err());
+  assert!(parse_timestamp("1518-11-01 24:05", DEFAULT_FORMATS).is_err());
+  assert!(parse_timestamp("1518-11-01 00:05", &[TimestampFormat::Iso8601]).is_err());
+  assert!(parse_timestamp("1518-11-01 00:05", &[]).is_err());
+}
@@ -0,0 +1,171 @@
+use chrono::prelude::*;
+use std::fmt;
+use {parse_action, Action, Entry};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+  /// Any anomaly fails the whole log.
+  Strict,
+  /// Anomalies are reported and the offending entries dropped.
+  Lenient,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AnomalyKind {
+  UnknownAction,
+  SleepBeforeShift,
+  WakeWithoutSleep,
+  DoubleSleep,
+  ShiftChangeWhileAsleep,
+  AsleepAtEndOfLog,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Anomaly {
+  pub line: usize,
+  pub date_time: DateTime<Utc>,
+  pub kind: AnomalyKind,
+}
+
+impl fmt::Display for Anomaly {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let description = match self.kind {
+      AnomalyKind::UnknownAction => "unknown action",
+      AnomalyKind::SleepBeforeShift => "falls asleep before any guard begins shift",
+      AnomalyKind::WakeWithoutSleep => "wakes up without falling asleep",
+      AnomalyKind::DoubleSleep => "falls asleep while already asleep",
+      AnomalyKind::ShiftChangeWhileAsleep => "shift changes while previous guard is asleep",
+      AnomalyKind::AsleepAtEndOfLog => "log ends while guard is asleep",
+    };
+    write!(f, "line {} [{}]: {}", self.line, self.date_time.format("%Y-%m-%d %H:%M"), description)
+  }
+}
+
+pub struct Validated<'a> {
+  pub entries: Vec<Entry<'a>>,
+  pub anomalies: Vec<Anomaly>,
+}
+
+/// The shift state machine, shared by `validate_log` and the streaming reader.
+#[derive(Debug, Default)]
+pub struct ShiftState {
+  on_duty: bool,
+  asleep: bool,
+}
+
+impl ShiftState {
+  /// Advances by one entry, returning the anomaly it causes and whether the entry is kept.
+  /// A shift change while asleep keeps the new shift but leaves the last sleep unmatched.
+  pub fn advance(&mut self, action: Action) -> (Option<AnomalyKind>, bool) {
+    match action {
+      Action::Unknown => (Some(AnomalyKind::UnknownAction), false),
+      Action::BeginShift(_) => {
+        let anomaly = if self.asleep { Some(AnomalyKind::ShiftChangeWhileAsleep) } else { None };
+        self.on_duty = true;
+        self.asleep = false;
+        (anomaly, true)
+      }
+      Action::FallsAsleep if !self.on_duty => (Some(AnomalyKind::SleepBeforeShift), false),
+      Action::FallsAsleep if self.asleep => (Some(AnomalyKind::DoubleSleep), false),
+      Action::FallsAsleep => {
+        self.asleep = true;
+        (None, true)
+      }
+      Action::WakesUp if !self.asleep => (Some(AnomalyKind::WakeWithoutSleep), false),
+      Action::WakesUp => {
+        self.asleep = false;
+        (None, true)
+      }
+    }
+  }
+
+  pub fn is_asleep(&self) -> bool {
+    self.asleep
+  }
+}
+
+/// Walks a time-sorted log through the shift state machine. In lenient mode unmatched
+/// sleeps and wakes are dropped so that every remaining "falls asleep" has a "wakes up".
+pub fn validate_log<'a>(log: &[Entry<'a>], mode: Mode) -> Result<Validated<'a>, Vec<Anomaly>> {
+  let mut entries: Vec<Entry<'a>> = vec![];
+  let mut anomalies = vec![];
+  let mut state = ShiftState::default();
+
+  let mut report = |entry: &Entry, kind| anomalies.push(Anomaly { line: entry.line, date_time: entry.date_time, kind });
+
+  for entry in log.iter() {
+    let (anomaly, keep) = state.advance(parse_action(entry.action));
+    if let Some(kind) = anomaly {
+      report(entry, kind);
+      if kind == AnomalyKind::ShiftChangeWhileAsleep {
+        entries.pop();
+      }
+    }
+    if keep {
+      entries.push(entry.clone());
+    }
+  }
+
+  if state.is_asleep() {
+    if let Some(last) = entries.pop() {
+      report(&last, AnomalyKind::AsleepAtEndOfLog);
+    }
+  }
+
+  if mode == Mode::Strict && !anomalies.is_empty() {
+    Err(anomalies)
+  } else {
+    Ok(Validated { entries, anomalies })
+  }
+}
+
+#[test]
+fn valid_log_test() {
+  let log = ::test_log(&["[1518-11-01 00:00] Guard #10 begins shift",
+                         "[1518-11-01 00:05] falls asleep",
+                         "[1518-11-01 00:25] wakes up"]);
+  let res = validate_log(&log, Mode::Strict).unwrap_or_else(|_| panic!("Valid log rejected"));
+  assert_eq!(res.entries, log);
+  assert!(res.anomalies.is_empty());
+}
+
+#[test]
+fn strict_anomalies_test() {
+  let log = ::test_log(&["[1518-11-01 00:01] falls asleep",
+                         "[1518-11-01 00:02] Guard #10 begins shift",
+                         "[1518-11-01 00:03] wakes up",
+                         "[1518-11-01 00:05] falls asleep",
+                         "[1518-11-01 00:06] falls asleep",
+                         "[1518-11-01 00:07] sings loudly",
+                         "[1518-11-01 00:25] wakes up",
+                         "[1518-11-01 00:30] falls asleep",
+                         "[1518-11-01 23:58] Guard #99 begins shift",
+                         "[1518-11-02 00:40] falls asleep"]);
+  let anomalies = match validate_log(&log, Mode::Strict) {
+    Ok(_) => panic!("Invalid log accepted"),
+    Err(anomalies) => anomalies,
+  };
+  let kinds = anomalies.iter().map(|a| (a.line, a.kind)).collect::<Vec<_>>();
+  assert_eq!(kinds, vec![(1, AnomalyKind::SleepBeforeShift),
+                         (3, AnomalyKind::WakeWithoutSleep),
+                         (5, AnomalyKind::DoubleSleep),
+                         (6, AnomalyKind::UnknownAction),
+                         (9, AnomalyKind::ShiftChangeWhileAsleep),
+                         (10, AnomalyKind::AsleepAtEndOfLog)]);
+  assert_eq!(format!("{}", anomalies[2]), "line 5 [1518-11-01 00:06]: falls asleep while already asleep");
+}
+
+#[test]
+fn lenient_repair_test() {
+  let log = ::test_log(&["[1518-11-01 00:00] Guard #10 begins shift",
+                         "[1518-11-01 00:03] wakes up",
+                         "[1518-11-01 00:05] falls asleep",
+                         "[1518-11-01 00:06] falls asleep",
+                         "[1518-11-01 00:25] wakes up",
+                         "[1518-11-01 00:30] falls asleep",
+                         "[1518-11-01 23:58] Guard #99 begins shift"]);
+  let res = validate_log(&log, Mode::Lenient).unwrap_or_else(|_| panic!("Lenient validation failed"));
+  let lines = res.entries.iter().map(|e| e.line).collect::<Vec<_>>();
+  assert_eq!(lines, vec![1, 3, 5, 7]);
+  assert_eq!(res.anomalies.len(), 3);
+}
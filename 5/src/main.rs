@@ -1,9 +1,21 @@
-use std::{fs::File, io::Read, path::Path};
+#[cfg(feature = "parallel")]
+extern crate rayon;
+
+mod reaction;
+mod search;
+mod stream;
+mod trace;
+
+use reaction::{AsciiCasePairs, PairTable, ReactionRule, UnicodeCasePairs};
+use search::{best_removal, smallest_removal_below, SearchStats};
+use stream::reduce_stream;
+use trace::{reduce_traced, Trace};
+use std::{env, fs::File, io, io::Read, path::Path};
 
 const CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
 fn read_input_file(filename: &str) -> String {
-  let mut file = File::open(&Path::new(filename))
+  let mut file = File::open(Path::new(filename))
     .unwrap_or_else(|e| panic!("File {} read error: {}", filename, e));
   let mut buf = String::new();
   file.read_to_string(&mut buf).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
@@ -17,17 +29,37 @@ fn line_to_chars(input: &str) -> Vec<String> {
     .collect::<Vec<String>>()
 }
 
+#[cfg(test)]
 fn is_uppercase(letter: &str) -> bool {
   CHARS.contains(letter)
 }
 
+#[cfg(test)]
 fn is_reacting(a: &str, b: &str) -> bool {
   ((is_uppercase(a) && !is_uppercase(b)) ||
     (!is_uppercase(a) && is_uppercase(b))) &&
-    a.to_ascii_lowercase() == b.to_ascii_lowercase()
+    a.eq_ignore_ascii_case(b)
 }
 
-fn parse_one_pass(input: &mut Vec<String>) -> Vec<String> {
+fn reduce<I: IntoIterator<Item=char>>(units: I) -> Vec<char> {
+  reduce_with(units, &AsciiCasePairs)
+}
+
+/// Reduces the polymer in a single pass: every unit either annihilates with the survivor
+/// on top of the stack or becomes the new top, so cascades need no further passes.
+fn reduce_with<I: IntoIterator<Item=char>, R: ReactionRule + ?Sized>(units: I, rule: &R) -> Vec<char> {
+  let mut stack: Vec<char> = vec![];
+  for unit in units {
+    match stack.last() {
+      Some(&top) if rule.reacts(top, unit) => { stack.pop(); }
+      _ => stack.push(unit),
+    }
+  }
+  stack
+}
+
+#[cfg(test)]
+fn parse_one_pass(input: &[String]) -> Vec<String> {
   let len = &(input.len() - 1);
   let mut idx_to_be_removed = vec![];
   for (idx, chr) in input.iter().enumerate() {
@@ -39,7 +71,7 @@ fn parse_one_pass(input: &mut Vec<String>) -> Vec<String> {
       idx_to_be_removed.push(idx + 1);
     }
   }
-  input.iter_mut().enumerate()
+  input.iter().enumerate()
     .map(|(idx, chr)|
       if idx_to_be_removed.contains(&idx) {
         "".to_string()
@@ -50,51 +82,211 @@ fn parse_one_pass(input: &mut Vec<String>) -> Vec<String> {
     .collect::<Vec<String>>()
 }
 
-fn remove_letter(input: &mut Vec<String>, letter: &str) -> Vec<String> {
+#[cfg(test)]
+fn remove_letter(input: &[String], letter: &str) -> Vec<String> {
   input.iter()
     .filter(|&chr| chr.to_ascii_uppercase() != letter)
     .map(|x| x.to_string())
     .collect::<Vec<String>>()
 }
 
-fn parse_input_by_letter(input: &mut Vec<String>, letter: &str) -> String {
+#[cfg(test)]
+fn parse_input_by_letter(input: &[String], letter: &str) -> String {
   let without_letter = remove_letter(input, letter);
   parse_input(without_letter)
 }
 
-fn find_problematic_letter(input: &mut Vec<String>) -> Vec<(String, usize)> {
-  let mut char_lens = line_to_chars(CHARS).iter()
-    .map(|chr| (chr.to_string(), parse_input_by_letter(input, chr).len()))
-    .collect::<Vec<(String, usize)>>();
-  char_lens.sort_by(|(_, a), (_, b)| a.cmp(b));
-  char_lens
+fn find_problematic_letter(input: &[String]) -> Vec<(String, usize)> {
+  let units = input.iter().flat_map(|el| el.chars()).collect::<Vec<char>>();
+  let letters = CHARS.chars().map(|chr| chr.to_ascii_lowercase()).collect::<Vec<char>>();
+  rank_unit_types(&units, &letters, &AsciiCasePairs).into_iter()
+    .map(|(letter, len)| (letter.to_ascii_uppercase().to_string(), len))
+    .collect::<Vec<(String, usize)>>()
+}
+
+/// Part two for any rule: removes each unit type present in the polymer in turn.
+fn find_problematic_unit_type<R: ReactionRule + ?Sized>(input: &[char], rule: &R) -> Vec<(char, usize)> {
+  let mut unit_types = input.iter().map(|&unit| rule.unit_type(unit)).collect::<Vec<char>>();
+  unit_types.sort();
+  unit_types.dedup();
+  rank_unit_types(input, &unit_types, rule)
+}
+
+/// The polymer to remove unit types from. Removing a type commutes with reduction when reacting
+/// units share a type and the rule is confluent, so then it is the already reduced polymer.
+/// Otherwise a reaction that a removed unit blocked could change the outcome, so it is the input.
+fn removal_base<R: ReactionRule + ?Sized>(input: &[char], rule: &R) -> Vec<char> {
+  if rule.is_confluent() {
+    reduce_with(input.iter().cloned(), rule)
+  } else {
+    input.to_vec()
+  }
+}
+
+/// Reduced lengths after removing each unit type, shortest first.
+fn rank_unit_types<R: ReactionRule + ?Sized>(input: &[char], unit_types: &[char], rule: &R) -> Vec<(char, usize)> {
+  let base = removal_base(input, rule);
+  let mut type_lens = map_candidates(unit_types, |unit_type| {
+    let remaining = base.iter().cloned().filter(|&unit| rule.unit_type(unit) != unit_type);
+    reduce_with(remaining, rule).len()
+  });
+  type_lens.sort_by_key(|&(unit_type, len)| (len, unit_type));
+  type_lens
+}
+
+#[cfg(feature = "parallel")]
+fn map_candidates<F: Fn(char) -> usize + Sync>(candidates: &[char], f: F) -> Vec<(char, usize)> {
+  use rayon::prelude::*;
+  candidates.par_iter()
+    .map(|&candidate| (candidate, f(candidate)))
+    .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_candidates<F: Fn(char) -> usize>(candidates: &[char], f: F) -> Vec<(char, usize)> {
+  candidates.iter()
+    .map(|&candidate| (candidate, f(candidate)))
+    .collect()
+}
+
+fn rule_by_name(name: &str) -> Box<dyn ReactionRule> {
+  match name {
+    "ascii" => Box::new(AsciiCasePairs),
+    "unicode" => Box::new(UnicodeCasePairs),
+    "brackets" => Box::new(PairTable::brackets()),
+    "directions" => Box::new(PairTable::opposite_directions()),
+    path => Box::new(PairTable::parse(&read_input_file(path)).unwrap_or_else(|e| panic!("{}", e))),
+  }
+}
+
+fn arg_value(name: &str) -> Option<String> {
+  env::args()
+    .skip_while(|arg| arg != name)
+    .nth(1)
+}
+
+fn parse_input(input: Vec<String>) -> String {
+  reduce(input.iter().flat_map(|el| el.chars()))
+    .into_iter()
+    .collect::<String>()
+}
+
+/// `parse_input` that also returns the trace of every reaction.
+fn parse_input_traced(input: &[String]) -> (String, Trace) {
+  let units = input.iter().flat_map(|el| el.chars()).collect::<Vec<char>>();
+  let trace = reduce_traced(&units, &AsciiCasePairs);
+  let reduced = trace.survivors.iter().map(|&pos| units[pos]).collect::<String>();
+  (reduced, trace)
+}
+
+fn print_trace(trace: &Trace, units: &[char]) {
+  if env::args().any(|arg| arg == "--trace") {
+    trace.reactions.iter().for_each(|reaction| {
+      println!("{}{} at {}..{} depth {}", reaction.units.0, reaction.units.1, reaction.left, reaction.right, reaction.depth);
+    });
+    println!("Reactions: {}, longest cascade: {}", trace.reactions.len(), trace.longest_cascade());
+    trace.reactions_per_type().iter().for_each(|(unit_type, count)| println!("  {} => {}", unit_type, count));
+  }
+  match arg_value("--explain").as_deref() {
+    Some("ansi") => println!("{}", trace.to_ansi(units)),
+    Some("html") => print!("{}", trace.to_html(units)),
+    Some(other) => panic!("Unknown explain format {}", other),
+    None => (),
+  }
+}
+
+fn print_stats(stats: &SearchStats) {
+  println!("  nodes: {}, reductions: {}, pruned: {}", stats.nodes, stats.reductions, stats.pruned);
 }
 
-fn parse_input(mut input: Vec<String>) -> String {
-  let mut len_a = input.len();
-  let mut len_b = 0;
-  while len_a > len_b {
-    len_a = input.len();
-    input = parse_one_pass(&mut input);
-    len_b = input.len();
+/// Removal of several unit types at once: `--remove k` for the best set of up to k types,
+/// `--below n` for the smallest set that gets the polymer below n units.
+fn print_search(units: &[char], rule: &dyn ReactionRule) {
+  if let Some(max_types) = arg_value("--remove") {
+    let max_types = max_types.parse::<usize>().unwrap_or_else(|e| panic!("Invalid --remove {}: {}", max_types, e));
+    let (best, stats) = best_removal(units, rule, max_types);
+    println!("Best removal of up to {} types: {:?} => {}", max_types, best.unit_types, best.len);
+    print_stats(&stats);
+  }
+  if let Some(target) = arg_value("--below") {
+    let target = target.parse::<usize>().unwrap_or_else(|e| panic!("Invalid --below {}: {}", target, e));
+    match smallest_removal_below(units, rule, target) {
+      (Some(removal), stats) => {
+        println!("Smallest removal below {}: {:?} => {}", target, removal.unit_types, removal.len);
+        print_stats(&stats);
+      }
+      (None, stats) => {
+        println!("No removal gets below {}", target);
+        print_stats(&stats);
+      }
+    }
   }
-  input.iter().map(|el| el.as_str()).collect::<String>()
+}
+
+/// Part one for polymers too large to load: `--stream path|-` and an optional `--output path|-`.
+fn print_streamed(source: &str, rule: &dyn ReactionRule) {
+  let input: Box<dyn Read> = match source {
+    "-" => Box::new(io::stdin()),
+    path => Box::new(File::open(Path::new(path)).unwrap_or_else(|e| panic!("File {} read error: {}", path, e))),
+  };
+  let res = match arg_value("--output").as_deref() {
+    Some("-") => {
+      let stdout = io::stdout();
+      let mut out = stdout.lock();
+      let res = reduce_stream(input, rule, Some(&mut out));
+      println!();
+      res
+    }
+    Some(path) => {
+      let mut out = File::create(Path::new(path)).unwrap_or_else(|e| panic!("File {} write error: {}", path, e));
+      reduce_stream(input, rule, Some(&mut out))
+    }
+    None => reduce_stream(input, rule, None::<&mut File>),
+  };
+  println!("Part one: {}", res.unwrap_or_else(|e| panic!("Stream read error: {}", e)));
 }
 
 fn main() {
+  if let Some(source) = arg_value("--stream") {
+    let rule = rule_by_name(&arg_value("--rule").unwrap_or_else(|| "ascii".to_string()));
+    print_streamed(&source, rule.as_ref());
+    return;
+  }
+
   let data = read_input_file("./input.txt");
-  let mut chars = line_to_chars(&data);
-  let res_a = parse_input(chars.clone());
+  if let Some(name) = arg_value("--rule") {
+    let rule = rule_by_name(&name);
+    let units = data.chars().filter(|chr| chr != &'\n').collect::<Vec<char>>();
+    let trace = reduce_traced(&units, rule.as_ref());
+    print_trace(&trace, &units);
+    println!("Part one: {}", trace.survivors.len());
+    let res_b = find_problematic_unit_type(&units, rule.as_ref());
+    println!("Part two: {:?}", res_b.first());
+    res_b.iter().for_each(|(unit_type, len)| println!("  {} => {}", unit_type, len));
+    print_search(&units, rule.as_ref());
+    return;
+  }
+
+  let chars = line_to_chars(&data);
+  let res_a = if env::args().any(|arg| arg == "--trace" || arg == "--explain") {
+    let (reduced, trace) = parse_input_traced(&chars);
+    print_trace(&trace, &data.chars().filter(|chr| chr != &'\n').collect::<Vec<char>>());
+    reduced
+  } else {
+    parse_input(chars.clone())
+  };
   println!("Part one: {}", res_a.chars().count());
-  let res_b = find_problematic_letter(&mut chars);
+  let res_b = find_problematic_letter(&chars);
   println!("Part two: {:?}", res_b[0]);
+  res_b.iter().for_each(|(letter, len)| println!("  {} => {}", letter, len));
+  print_search(&data.chars().filter(|chr| chr != &'\n').collect::<Vec<char>>(), &AsciiCasePairs);
 }
 
 #[test]
 fn one_pass_test() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let res = parse_one_pass(&mut chars).into_iter().collect::<String>();
+  let chars = line_to_chars(input);
+  let res = parse_one_pass(&chars).into_iter().collect::<String>();
   assert_eq!(res, "dabAaCBAcaDA".to_string());
 }
 
@@ -115,48 +307,95 @@ fn basic_test() {
   assert_eq!(res.chars().count(), 10)
 }
 
+#[test]
+fn traced_test() {
+  let chars = line_to_chars("dabAcCaCBAcCcaDA");
+  let (res, trace) = parse_input_traced(&chars);
+  assert_eq!(res, parse_input(chars));
+  assert_eq!(trace.reactions.len(), 3);
+}
+
 #[test]
 fn second_part_a() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let without_a = remove_letter(&mut chars, "A").into_iter().collect::<String>();
+  let chars = line_to_chars(input);
+  let without_a = remove_letter(&chars, "A").into_iter().collect::<String>();
   assert_eq!(without_a, "dbcCCBcCcD");
-  let res = parse_input_by_letter(&mut chars, "A");
+  let res = parse_input_by_letter(&chars, "A");
   assert_eq!(res, "dbCBcD");
 }
 
 #[test]
 fn second_part_b() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let without_b = remove_letter(&mut chars, "B").into_iter().collect::<String>();
+  let chars = line_to_chars(input);
+  let without_b = remove_letter(&chars, "B").into_iter().collect::<String>();
   assert_eq!(without_b, "daAcCaCAcCcaDA");
 
-  let res = parse_input_by_letter(&mut chars, "B");
+  let res = parse_input_by_letter(&chars, "B");
   assert_eq!(res, "daCAcaDA");
 }
 
 #[test]
 fn second_part_c() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let res = parse_input_by_letter(&mut chars, "C");
+  let chars = line_to_chars(input);
+  let res = parse_input_by_letter(&chars, "C");
   assert_eq!(res, "daDA");
 }
 
 #[test]
 fn second_part_d() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let res = parse_input_by_letter(&mut chars, "D");
+  let chars = line_to_chars(input);
+  let res = parse_input_by_letter(&chars, "D");
   assert_eq!(res, "abCBAc");
 }
 
 #[test]
 fn second_part_combined() {
   let input = "dabAcCaCBAcCcaDA";
-  let mut chars = line_to_chars(input);
-  let res = find_problematic_letter(&mut chars);
+  let chars = line_to_chars(input);
+  let res = find_problematic_letter(&chars);
   assert_eq!(res[0].0, "C");
   assert_eq!(res[0].1, 4);
 }
+
+#[test]
+fn ranked_table_test() {
+  let chars = line_to_chars("dabAcCaCBAcCcaDA");
+  let res = find_problematic_letter(&chars);
+  assert_eq!(res.len(), 26);
+  let head = res.iter().take(5).map(|(letter, len)| (letter.as_str(), *len)).collect::<Vec<_>>();
+  assert_eq!(head, vec![("C", 4), ("A", 6), ("D", 6), ("B", 8), ("E", 10)]);
+}
+
+#[test]
+fn reduce_test() {
+  assert_eq!(reduce("aA".chars()), vec![]);
+  assert_eq!(reduce("abBA".chars()), vec![]);
+  assert_eq!(reduce("abAB".chars()), "abAB".chars().collect::<Vec<_>>());
+  assert_eq!(reduce("aabAAB".chars()), "aabAAB".chars().collect::<Vec<_>>());
+  assert_eq!(reduce("a-A".chars()), "a-A".chars().collect::<Vec<_>>());
+}
+
+#[test]
+fn rules_test() {
+  let brackets = PairTable::brackets();
+  assert_eq!(reduce_with("([{}])(]".chars(), &brackets), vec!['(', ']']);
+  assert_eq!(reduce_with("NEWSSN".chars(), &PairTable::opposite_directions()), vec![]);
+  assert_eq!(reduce_with("äÖöÄ".chars(), &UnicodeCasePairs), vec![]);
+
+  let units = "dabAcCaCBAcCcaDA".chars().collect::<Vec<char>>();
+  let res = find_problematic_unit_type(&units, &AsciiCasePairs);
+  assert_eq!(res[0], ('c', 4));
+  assert_eq!(find_problematic_unit_type(&"(x)[y]".chars().collect::<Vec<char>>(), &brackets)[0], ('x', 3));
+}
+
+#[test]
+fn non_confluent_rule_test() {
+  let chain = PairTable::parse("a b\nb c\nc d").unwrap();
+  let units = "axbcd".chars().collect::<Vec<char>>();
+  assert_eq!(reduce_with(units.iter().cloned(), &chain), vec!['a', 'x', 'd']);
+  assert_eq!(find_problematic_unit_type(&units, &chain), vec![('x', 0), ('a', 1)]);
+}
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+
+pub trait ReactionRule: Sync {
+  /// Whether unit `a` immediately followed by unit `b` annihilates.
+  fn reacts(&self, a: char, b: char) -> bool;
+
+  /// The type a unit belongs to when removing whole unit types. Units that react
+  /// with each other must share a type.
+  fn unit_type(&self, unit: char) -> char {
+    unit.to_ascii_lowercase()
+  }
+
+  /// A charge that reactions conserve: two reacting units carry opposite charges.
+  /// Rules without such a charge leave it at zero.
+  fn charge(&self, _unit: char) -> i32 {
+    0
+  }
+
+  /// Whether every order of reactions ends in the same polymer. Only then can a unit type be
+  /// removed from an already reduced polymer. Rules that cannot tell keep the default and are
+  /// treated as not confluent.
+  fn is_confluent(&self) -> bool {
+    false
+  }
+}
+
+/// The puzzle rule: the same ASCII letter in opposite case.
+pub struct AsciiCasePairs;
+
+/// The same letter in opposite case for any alphabet with Unicode case mapping. Not confluent in
+/// general: both `K` and the Kelvin sign `K` react with `k`.
+pub struct UnicodeCasePairs;
+
+/// An explicit table of ordered pairs, e.g. `(` followed by `)`.
+#[derive(Debug, Default)]
+pub struct PairTable {
+  pairs: HashSet<(char, char)>,
+  types: HashMap<char, char>,
+}
+
+impl ReactionRule for AsciiCasePairs {
+  fn reacts(&self, a: char, b: char) -> bool {
+    a != b && a.eq_ignore_ascii_case(&b)
+  }
+
+  fn charge(&self, unit: char) -> i32 {
+    if unit.is_ascii_uppercase() { 1 } else { -1 }
+  }
+
+  fn is_confluent(&self) -> bool {
+    true
+  }
+}
+
+impl ReactionRule for UnicodeCasePairs {
+  fn reacts(&self, a: char, b: char) -> bool {
+    a.is_uppercase() != b.is_uppercase() && a.to_lowercase().eq(b.to_lowercase())
+  }
+
+  fn unit_type(&self, unit: char) -> char {
+    unit.to_lowercase().next().unwrap_or(unit)
+  }
+
+  fn charge(&self, unit: char) -> i32 {
+    if unit.is_uppercase() { 1 } else { -1 }
+  }
+}
+
+impl ReactionRule for PairTable {
+  fn reacts(&self, a: char, b: char) -> bool {
+    self.pairs.contains(&(a, b))
+  }
+
+  fn unit_type(&self, unit: char) -> char {
+    let mut root = unit;
+    while let Some(&parent) = self.types.get(&root) {
+      if parent == root {
+        break;
+      }
+      root = parent;
+    }
+    root
+  }
+
+  /// A reaction removes two adjacent units, so two reactions only compete in `a b c` where both
+  /// `a b` and `b c` react. Either leaves a single unit, so the table is confluent exactly when
+  /// every such overlap has `a == c`.
+  fn is_confluent(&self) -> bool {
+    self.pairs.iter().all(|&(a, b)| self.pairs.iter().all(|&(left, c)| left != b || c == a))
+  }
+}
+
+impl PairTable {
+  pub fn new() -> Self {
+    PairTable::default()
+  }
+
+  /// Adds a reaction of `a` immediately followed by `b`. Both units become the same unit type.
+  pub fn with_pair(mut self, a: char, b: char) -> Self {
+    self.pairs.insert((a, b));
+    let (type_a, type_b) = (self.unit_type(a), self.unit_type(b));
+    let (root, child) = if type_a <= type_b { (type_a, type_b) } else { (type_b, type_a) };
+    self.types.insert(child, root);
+    self.types.entry(root).or_insert(root);
+    self
+  }
+
+  /// Adds reactions for `a` followed by `b` and `b` followed by `a`.
+  pub fn with_symmetric_pair(self, a: char, b: char) -> Self {
+    self.with_pair(a, b).with_pair(b, a)
+  }
+
+  /// Opening brackets annihilate with the matching closing bracket that follows them.
+  pub fn brackets() -> Self {
+    PairTable::new()
+      .with_pair('(', ')')
+      .with_pair('[', ']')
+      .with_pair('{', '}')
+      .with_pair('<', '>')
+  }
+
+  /// Moves in opposite directions cancel out, e.g. `NS` or `EW`.
+  pub fn opposite_directions() -> Self {
+    PairTable::new()
+      .with_symmetric_pair('N', 'S')
+      .with_symmetric_pair('E', 'W')
+      .with_symmetric_pair('U', 'D')
+  }
+
+  /// Parses one pair per line as two units separated by whitespace, `a b` meaning that
+  /// `a` immediately followed by `b` reacts. Empty lines and `#` comments are skipped.
+  pub fn parse(input: &str) -> Result<Self, String> {
+    input.lines()
+      .enumerate()
+      .map(|(idx, line)| (idx + 1, line.trim()))
+      .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+      .try_fold(PairTable::new(), |table, (line_no, line)| {
+        let units = line.split_whitespace().collect::<Vec<&str>>();
+        let as_unit = |token: &str| {
+          let mut chars = token.chars();
+          match (chars.next(), chars.next()) {
+            (Some(unit), None) => Some(unit),
+            _ => None,
+          }
+        };
+        match (units.len(), units.first().and_then(|u| as_unit(u)), units.get(1).and_then(|u| as_unit(u))) {
+          (2, Some(a), Some(b)) => Ok(table.with_pair(a, b)),
+          _ => Err(format!("Invalid pair on line {}: {:?}", line_no, line)),
+        }
+      })
+  }
+}
+
+#[test]
+fn case_rules_test() {
+  assert!(AsciiCasePairs.reacts('a', 'A'));
+  assert!(!AsciiCasePairs.reacts('a', 'a'));
+  assert!(!AsciiCasePairs.reacts('ä', 'Ä'));
+  assert!(UnicodeCasePairs.reacts('ä', 'Ä'));
+  assert!(UnicodeCasePairs.reacts('Σ', 'σ'));
+  assert!(!UnicodeCasePairs.reacts('σ', 'σ'));
+  assert!(!UnicodeCasePairs.reacts('-', '-'));
+  assert_eq!(UnicodeCasePairs.unit_type('Ä'), 'ä');
+}
+
+#[test]
+fn pair_table_test() {
+  let brackets = PairTable::brackets();
+  assert!(brackets.reacts('(', ')'));
+  assert!(!brackets.reacts(')', '('));
+  assert_eq!(brackets.unit_type(')'), brackets.unit_type('('));
+  assert_ne!(brackets.unit_type(']'), brackets.unit_type(')'));
+
+  let directions = PairTable::opposite_directions();
+  assert!(directions.reacts('S', 'N'));
+  assert_eq!(directions.unit_type('W'), 'E');
+
+  let parsed = PairTable::parse("# moves\nL R\nR L\n\n+ -").unwrap();
+  assert!(parsed.reacts('R', 'L'));
+  assert!(parsed.reacts('+', '-'));
+  assert!(!parsed.reacts('-', '+'));
+  assert_eq!(PairTable::parse("L R\nLR").unwrap_err(), "Invalid pair on line 2: \"LR\"");
+}
+
+#[test]
+fn confluence_test() {
+  assert!(AsciiCasePairs.is_confluent());
+  assert!(!UnicodeCasePairs.is_confluent());
+  assert!(PairTable::brackets().is_confluent());
+  assert!(PairTable::opposite_directions().is_confluent());
+  assert!(!PairTable::parse("a b\nb c").unwrap().is_confluent());
+  assert!(!PairTable::new().with_pair('a', 'b').with_pair('b', 'b').is_confluent());
+}
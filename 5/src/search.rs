@@ -0,0 +1,187 @@
+use reaction::ReactionRule;
+use {reduce_with, removal_base};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Removal {
+  pub unit_types: Vec<char>,
+  pub len: usize,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct SearchStats {
+  /// Sets of unit types whose reduced length was computed.
+  pub nodes: usize,
+  pub reductions: usize,
+  /// Branches skipped because their lower bound could not beat the best set found so far.
+  pub pruned: usize,
+}
+
+struct Search<'a, R: ReactionRule + ?Sized + 'a> {
+  rule: &'a R,
+  /// Whether each set is removed from its parent's reduced polymer rather than the unreduced one.
+  confluent: bool,
+  order: Vec<char>,
+  max_types: usize,
+  limit: usize,
+  best: Removal,
+  stats: SearchStats,
+}
+
+/// The fewest units of each type that no further reaction can remove: reactions take out two
+/// units of one type with opposite charges, so the parity and the net charge of a type survive.
+fn residues<R: ReactionRule + ?Sized>(units: &[char], rule: &R) -> HashMap<char, usize> {
+  let mut totals: HashMap<char, (usize, i64)> = HashMap::new();
+  units.iter().for_each(|&unit| {
+    let total = totals.entry(rule.unit_type(unit)).or_insert((0, 0));
+    total.0 += 1;
+    total.1 += rule.charge(unit) as i64;
+  });
+  totals.into_iter()
+    .map(|(unit_type, (count, charge))| (unit_type, (charge.unsigned_abs() as usize).max(count % 2)))
+    .collect()
+}
+
+impl<'a, R: ReactionRule + ?Sized> Search<'a, R> {
+  /// Extends `chosen` with unit types from `order[start..]`. `current` is the polymer with the
+  /// chosen types removed, reduced only for a confluent rule. Reactions keep the residues of every
+  /// type either way, so the bound holds for both.
+  fn explore(&mut self, current: &[char], start: usize, chosen: &mut Vec<char>) {
+    if chosen.len() == self.max_types || current.is_empty() {
+      return;
+    }
+    let residues = residues(current, self.rule);
+    let total = residues.values().sum::<usize>();
+    let slots = self.max_types - chosen.len();
+    for idx in start..self.order.len() {
+      let unit_type = self.order[idx];
+      let own = match residues.get(&unit_type) {
+        Some(&own) => own,
+        None => continue,
+      };
+      let mut gains = self.order[idx + 1..].iter()
+        .filter_map(|other| residues.get(other).cloned())
+        .collect::<Vec<usize>>();
+      gains.sort_by_key(|&gain| Reverse(gain));
+      let bound = total - own - gains.iter().take(slots - 1).sum::<usize>();
+      let beaten = bound > self.best.len ||
+        (bound == self.best.len && chosen.len() + 1 >= self.best.unit_types.len());
+      if bound >= self.limit || beaten {
+        self.stats.pruned += 1;
+        continue;
+      }
+
+      let rule = self.rule;
+      let remaining = current.iter().cloned().filter(|&unit| rule.unit_type(unit) != unit_type).collect::<Vec<char>>();
+      let reduced = reduce_with(remaining.iter().cloned(), rule);
+      self.stats.reductions += 1;
+      self.stats.nodes += 1;
+      chosen.push(unit_type);
+      if reduced.len() < self.best.len || (reduced.len() == self.best.len && chosen.len() < self.best.unit_types.len()) {
+        self.best = Removal { unit_types: chosen.clone(), len: reduced.len() };
+      }
+      let next = if self.confluent { reduced } else { remaining };
+      self.explore(&next, idx + 1, chosen);
+      chosen.pop();
+    }
+  }
+}
+
+/// Picks the polymer to remove unit types from, as `removal_base` does, and orders its unit types
+/// by their single removal length, so that good sets are found early and tighten the bound for
+/// the rest of the search.
+fn prepare<R: ReactionRule + ?Sized>(input: &[char], rule: &R, stats: &mut SearchStats) -> (Vec<char>, Vec<char>) {
+  let base = removal_base(input, rule);
+  let mut unit_types = base.iter().map(|&unit| rule.unit_type(unit)).collect::<Vec<char>>();
+  unit_types.sort();
+  unit_types.dedup();
+  let mut singles = unit_types.into_iter()
+    .map(|unit_type| {
+      let remaining = base.iter().cloned().filter(|&unit| rule.unit_type(unit) != unit_type);
+      (reduce_with(remaining, rule).len(), unit_type)
+    })
+    .collect::<Vec<(usize, char)>>();
+  singles.sort();
+  stats.reductions += 1 + singles.len();
+  (base, singles.into_iter().map(|(_, unit_type)| unit_type).collect())
+}
+
+fn run<R: ReactionRule + ?Sized>(base: &[char], order: &[char], rule: &R, max_types: usize, limit: usize,
+                                 stats: &mut SearchStats) -> Removal {
+  let confluent = rule.is_confluent();
+  let len = if confluent { base.len() } else { reduce_with(base.iter().cloned(), rule).len() };
+  let mut search = Search {
+    rule,
+    confluent,
+    order: order.to_vec(),
+    max_types,
+    limit,
+    best: Removal { unit_types: vec![], len },
+    stats: SearchStats { nodes: stats.nodes + 1, ..*stats },
+  };
+  search.explore(base, 0, &mut vec![]);
+  *stats = search.stats;
+  let mut best = search.best;
+  best.unit_types.sort();
+  best
+}
+
+/// The set of at most `max_types` unit types whose removal leaves the shortest polymer,
+/// preferring fewer types among equally short results.
+pub fn best_removal<R: ReactionRule + ?Sized>(input: &[char], rule: &R, max_types: usize) -> (Removal, SearchStats) {
+  let mut stats = SearchStats::default();
+  let (base, order) = prepare(input, rule, &mut stats);
+  let best = run(&base, &order, rule, max_types, usize::MAX, &mut stats);
+  (best, stats)
+}
+
+/// The smallest set of unit types whose removal leaves fewer than `target` units, searching
+/// set sizes in increasing order. Returns the shortest result among sets of that size.
+pub fn smallest_removal_below<R: ReactionRule + ?Sized>(input: &[char], rule: &R, target: usize)
+                                                        -> (Option<Removal>, SearchStats) {
+  let mut stats = SearchStats::default();
+  let (base, order) = prepare(input, rule, &mut stats);
+  for max_types in 0..=order.len() {
+    let best = run(&base, &order, rule, max_types, target, &mut stats);
+    if best.len < target {
+      return (Some(best), stats);
+    }
+  }
+  (None, stats)
+}
+
+#[test]
+fn best_removal_test() {
+  let units = "dabAcCaCBAcCcaDA".chars().collect::<Vec<char>>();
+  let rule = ::reaction::AsciiCasePairs;
+  let (single, _) = best_removal(&units, &rule, 1);
+  assert_eq!(single, Removal { unit_types: vec!['c'], len: 4 });
+
+  let (pair, stats) = best_removal(&units, &rule, 3);
+  assert_eq!(pair.len, 0);
+  assert_eq!(pair.unit_types.len(), 2);
+  let remaining = units.iter().cloned().filter(|&unit| !pair.unit_types.contains(&unit.to_ascii_lowercase()));
+  assert!(reduce_with(remaining, &rule).is_empty());
+  assert!(stats.pruned > 0);
+  assert_eq!(best_removal(&units, &rule, 0).0, Removal { unit_types: vec![], len: 10 });
+}
+
+#[test]
+fn smallest_removal_test() {
+  let units = "dabAcCaCBAcCcaDA".chars().collect::<Vec<char>>();
+  let rule = ::reaction::AsciiCasePairs;
+  assert_eq!(smallest_removal_below(&units, &rule, 11).0, Some(Removal { unit_types: vec![], len: 10 }));
+  assert_eq!(smallest_removal_below(&units, &rule, 5).0, Some(Removal { unit_types: vec!['c'], len: 4 }));
+  assert_eq!(smallest_removal_below(&units, &rule, 1).0.map(|removal| removal.unit_types.len()), Some(2));
+  assert_eq!(smallest_removal_below(&units, &rule, 0).0, None);
+}
+
+#[test]
+fn non_confluent_search_test() {
+  let units = "axbcd".chars().collect::<Vec<char>>();
+  let chain = ::reaction::PairTable::parse("a b\nb c\nc d").unwrap();
+  assert_eq!(best_removal(&units, &chain, 1).0, Removal { unit_types: vec!['x'], len: 0 });
+  assert_eq!(best_removal(&units, &chain, 2).0, Removal { unit_types: vec!['x'], len: 0 });
+  assert_eq!(smallest_removal_below(&units, &chain, 1).0, Some(Removal { unit_types: vec!['x'], len: 0 }));
+}
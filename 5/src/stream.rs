@@ -0,0 +1,83 @@
+use reaction::ReactionRule;
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+use std::str;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reduces a polymer read from `input` without loading it, keeping only the stack of survivors.
+/// Line breaks are skipped. Returns the reduced length and writes the reduced polymer to `output`.
+pub fn reduce_stream<In, Out, R>(input: In, rule: &R, output: Option<&mut Out>) -> io::Result<usize>
+  where In: Read, Out: Write, R: ReactionRule + ?Sized {
+  reduce_chunked(input, rule, output, CHUNK_SIZE)
+}
+
+fn reduce_chunked<In, Out, R>(mut input: In, rule: &R, output: Option<&mut Out>, chunk_size: usize) -> io::Result<usize>
+  where In: Read, Out: Write, R: ReactionRule + ?Sized {
+  // A UTF-8 sequence cut by the chunk boundary leaves at most three bytes behind.
+  let mut buf = vec![0u8; chunk_size.max(4)];
+  let mut carry = 0;
+  let mut stack: Vec<char> = vec![];
+  loop {
+    let read = match input.read(&mut buf[carry..]) {
+      Ok(read) => read,
+      Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    };
+    if read == 0 {
+      if carry > 0 {
+        return Err(io::Error::new(ErrorKind::InvalidData, "polymer ends inside a UTF-8 sequence"));
+      }
+      break;
+    }
+    let filled = carry + read;
+    let valid = match str::from_utf8(&buf[..filled]) {
+      Ok(text) => text,
+      Err(e) => match e.error_len() {
+        None => str::from_utf8(&buf[..e.valid_up_to()]).unwrap_or_default(),
+        Some(_) => return Err(io::Error::new(ErrorKind::InvalidData, e)),
+      },
+    };
+    for unit in valid.chars().filter(|&unit| unit != '\n' && unit != '\r') {
+      match stack.last() {
+        Some(&top) if rule.reacts(top, unit) => { stack.pop(); }
+        _ => stack.push(unit),
+      }
+    }
+    let consumed = valid.len();
+    buf.copy_within(consumed..filled, 0);
+    carry = filled - consumed;
+  }
+
+  if let Some(output) = output {
+    let mut writer = BufWriter::new(output);
+    let mut encoded = [0u8; 4];
+    for unit in stack.iter() {
+      writer.write_all(unit.encode_utf8(&mut encoded).as_bytes())?;
+    }
+    writer.flush()?;
+  }
+  Ok(stack.len())
+}
+
+#[test]
+fn stream_test() {
+  let mut reduced = vec![];
+  let len = reduce_stream("dabAcCaC\nBAcCcaDA\n".as_bytes(), &::reaction::AsciiCasePairs, Some(&mut reduced)).unwrap();
+  assert_eq!(len, 10);
+  assert_eq!(reduced, b"dabCBAcaDA");
+  assert_eq!(reduce_stream("aA".as_bytes(), &::reaction::AsciiCasePairs, None::<&mut Vec<u8>>).unwrap(), 0);
+}
+
+#[test]
+fn split_sequence_test() {
+  let input = "x€äΣöÖσÄ€y\n";
+  for chunk_size in 4..8 {
+    let mut reduced = vec![];
+    let len = reduce_chunked(input.as_bytes(), &::reaction::UnicodeCasePairs, Some(&mut reduced), chunk_size).unwrap();
+    assert_eq!(len, 4);
+    assert_eq!(String::from_utf8(reduced).unwrap(), "x€€y");
+  }
+  let truncated = &input.as_bytes()[..2];
+  assert!(reduce_stream(truncated, &::reaction::UnicodeCasePairs, None::<&mut Vec<u8>>).is_err());
+  assert!(reduce_stream(&b"a\xffA"[..], &::reaction::AsciiCasePairs, None::<&mut Vec<u8>>).is_err());
+}
@@ -0,0 +1,119 @@
+use reaction::ReactionRule;
+use std::collections::HashMap;
+
+const ANSI_SURVIVOR: &str = "\x1b[1;32m";
+const ANSI_REACTED: &str = "\x1b[2m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reaction {
+  /// Positions of the two units in the original polymer.
+  pub left: usize,
+  pub right: usize,
+  pub units: (char, char),
+  pub unit_type: char,
+  /// 1 for neighbours in the original polymer, otherwise one more than the deepest
+  /// reaction that had to happen between them first.
+  pub depth: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Trace {
+  /// Reactions in the order they happened.
+  pub reactions: Vec<Reaction>,
+  /// Positions of the surviving units in the original polymer.
+  pub survivors: Vec<usize>,
+}
+
+/// Same reduction as `reduce_with`, but records every reaction. Each stack entry remembers the
+/// deepest reaction that happened to its right since it was pushed, which gives the cascade depth.
+pub fn reduce_traced<R: ReactionRule + ?Sized>(units: &[char], rule: &R) -> Trace {
+  let mut stack: Vec<(usize, usize)> = vec![];
+  let mut reactions = vec![];
+  for (pos, &unit) in units.iter().enumerate() {
+    match stack.last() {
+      Some(&(top, inner)) if rule.reacts(units[top], unit) => {
+        stack.pop();
+        let depth = inner + 1;
+        reactions.push(Reaction { left: top, right: pos, units: (units[top], unit), unit_type: rule.unit_type(unit), depth });
+        if let Some(below) = stack.last_mut() {
+          below.1 = below.1.max(depth);
+        }
+      }
+      _ => stack.push((pos, 0)),
+    }
+  }
+  Trace {
+    reactions,
+    survivors: stack.into_iter().map(|(pos, _)| pos).collect(),
+  }
+}
+
+impl Trace {
+  pub fn longest_cascade(&self) -> usize {
+    self.reactions.iter().map(|reaction| reaction.depth).max().unwrap_or(0)
+  }
+
+  /// Reaction counts per unit type, most reactive first.
+  pub fn reactions_per_type(&self) -> Vec<(char, usize)> {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    self.reactions.iter().for_each(|reaction| *counts.entry(reaction.unit_type).or_insert(0) += 1);
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by_key(|&(unit_type, count)| (std::cmp::Reverse(count), unit_type));
+    counts
+  }
+
+  fn runs(&self, units: &[char]) -> Vec<(bool, String)> {
+    let mut surviving = vec![false; units.len()];
+    self.survivors.iter().for_each(|&pos| surviving[pos] = true);
+    let mut runs: Vec<(bool, String)> = vec![];
+    for (&unit, &survived) in units.iter().zip(surviving.iter()) {
+      match runs.last_mut() {
+        Some((run_survived, run)) if *run_survived == survived => run.push(unit),
+        _ => runs.push((survived, unit.to_string())),
+      }
+    }
+    runs
+  }
+
+  /// The original polymer with survivors in bold green and reacted units dimmed.
+  pub fn to_ansi(&self, units: &[char]) -> String {
+    self.runs(units).iter()
+      .map(|(survived, run)| format!("{}{}{}", if *survived { ANSI_SURVIVOR } else { ANSI_REACTED }, run, ANSI_RESET))
+      .collect::<String>()
+  }
+
+  /// The original polymer as an HTML fragment with `survivor` and `reacted` spans.
+  pub fn to_html(&self, units: &[char]) -> String {
+    let spans = self.runs(units).iter()
+      .map(|(survived, run)| {
+        let escaped = run.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        format!("<span class=\"{}\">{}</span>", if *survived { "survivor" } else { "reacted" }, escaped)
+      })
+      .collect::<String>();
+    format!("<pre class=\"polymer\">{}</pre>\n", spans)
+  }
+}
+
+#[test]
+fn trace_test() {
+  let units = "dabAcCaCBAcCcaDA".chars().collect::<Vec<char>>();
+  let trace = reduce_traced(&units, &::reaction::AsciiCasePairs);
+  let events = trace.reactions.iter().map(|r| (r.left, r.right, r.depth)).collect::<Vec<_>>();
+  assert_eq!(events, vec![(4, 5, 1), (3, 6, 2), (10, 11, 1)]);
+  assert_eq!(trace.reactions[1].units, ('A', 'a'));
+  assert_eq!(trace.survivors.iter().map(|&pos| units[pos]).collect::<String>(), "dabCBAcaDA");
+  assert_eq!(trace.longest_cascade(), 2);
+  assert_eq!(trace.reactions_per_type(), vec![('c', 2), ('a', 1)]);
+}
+
+#[test]
+fn explain_test() {
+  let units = "xaAb<(>".chars().collect::<Vec<char>>();
+  let trace = reduce_traced(&units, &::reaction::AsciiCasePairs);
+  assert_eq!(trace.to_html(&units),
+             "<pre class=\"polymer\"><span class=\"survivor\">x</span><span class=\"reacted\">aA</span>\
+              <span class=\"survivor\">b&lt;(&gt;</span></pre>\n");
+  assert_eq!(trace.to_ansi(&units),
+             "\x1b[1;32mx\x1b[0m\x1b[2maA\x1b[0m\x1b[1;32mb<(>\x1b[0m");
+}
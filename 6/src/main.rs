@@ -1,9 +1,18 @@
-use std::{fs::File, io::Read, path::Path};
+mod metric;
+mod render;
+mod spatial;
+
+use geometry::{Grid, Point, Rect};
+use metric::{metric_by_name, Manhattan, Metric};
+use render::{rasterize, render_svg, write_ppm};
+use spatial::SiteIndex;
+use std::{env, fs::File, io, io::Read, path::Path};
 
 const CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const RENDER_SCALE: usize = 2;
 
 fn read_input_file(filename: &str) -> String {
-  let mut file = File::open(&Path::new(filename))
+  let mut file = File::open(Path::new(filename))
     .unwrap_or_else(|e| panic!("File {} read error: {}", filename, e));
   let mut buf = String::new();
   file.read_to_string(&mut buf).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
@@ -15,201 +24,305 @@ fn lines_to_vec(input: &str) -> Vec<&str> {
     .collect::<Vec<&str>>()
 }
 
-fn line_to_chars(input: &str) -> Vec<char> {
-  input.chars()
-    .filter(|chr| chr != &'\n')
-    .collect::<Vec<char>>()
-}
-
-fn line_to_positions(lines: &mut Vec<&str>) -> Vec<(i32, i32)> {
+fn line_to_positions(lines: &mut Vec<&str>) -> Vec<Point> {
   lines.iter()
     .map(|&line| {
       let nums = line.split(", ")
         .map(|x| x.parse::<i32>().unwrap_or_else(|_| panic!("Number cast error {}", x)))
         .collect::<Vec<i32>>();
-      (nums[0], nums[1])
+      Point::new(nums[0], nums[1])
     })
-    .collect::<Vec<(i32, i32)>>()
-}
-
-fn calc_nth(x: i32, y: i32) -> i32 {
-  (y * 1000 + x)
-}
-
-fn position_to_grid_tuples(coords: &Vec<(i32, i32)>) -> Vec<(bool, char, i32, i32, usize)> {
-  let mut sign = ' ';
-  coords.iter()
-    .enumerate()
-    .map(|(idx, &coord)| {
-      sign = *line_to_chars(CHARS)
-        .get(idx)
-        .unwrap_or_else(|| panic!("Indexing error {}", idx));
-      (true,
-       sign,
-       coord.0,
-       coord.1,
-       0,
-      )
-    })
-    .collect::<Vec<_>>()
-}
-
-fn mark_duplicates(new_ones: &[(bool, char, i32, i32, usize)]) -> Vec<(bool, char, i32, i32, usize)> {
-  let mut duplicates = vec![];
-  let mut idx = 0;
-  new_ones.iter().for_each(|(_, x1_sign, x1, y1, _)| {
-    if let Some(val) = new_ones.iter()
-      .skip(idx)
-      .take_while(|(_, _, _, y2, _)| y2 <= y1)
-      .find(|(_, x2_sign, x2, y2, _)| y1 == y2 && x1 == x2 && x1_sign != x2_sign) {
-      duplicates.push((true, '.', val.2, val.3, val.4));
-    }
-    idx += 1;
-  });
-  duplicates
+    .collect::<Vec<Point>>()
 }
 
-fn fill_one_pass(start_coords: Vec<(bool, char, i32, i32, usize)>) -> Vec<(bool, char, i32, i32, usize)> {
-  let mut new_ones = vec![];
-  for el in start_coords.iter().filter(|&el| el.0) {
-    let nth_pass = el.4 + 1;
-    let sign = el.1;
-    new_ones.push((false, sign, (el.2), (el.3 - 1), nth_pass));
-    new_ones.push((false, sign, (el.2 - 1), (el.3), nth_pass));
-    new_ones.push((false, sign, (el.2), (el.3 + 1), nth_pass));
-    new_ones.push((false, sign, (el.2 + 1), (el.3), nth_pass));
-  }
+/// Single character label for rendering. Labels repeat after 52 sites.
+fn site_label(site_id: usize) -> char {
+  CHARS.chars()
+    .nth(site_id % CHARS.len())
+    .unwrap_or_else(|| panic!("Indexing error {}", site_id))
+}
 
-  let mut filled_ones = start_coords.iter()
-    .map(|pos| (true, pos.1, pos.2, pos.3, pos.4))
-    .collect::<Vec<_>>();
+/// Unique label for any number of sites: `A`..`z`, then `AA`, `AB` and so on.
+fn site_tag(site_id: usize) -> String {
+  let mut tag = vec![];
+  let mut rest = site_id + 1;
+  while rest > 0 {
+    rest -= 1;
+    tag.push(site_label(rest % CHARS.len()));
+    rest /= CHARS.len();
+  }
+  tag.into_iter().rev().collect::<String>()
+}
 
-  new_ones.sort_by(|(_, _, a1, a2, _), (_, _, b1, b2, _)| {
-    let a = calc_nth(*a1, *a2);
-    let b = calc_nth(*b1, *b2);
-    a.cmp(&b)
-  });
+/// A cell of the nearest-site partition. Sites are numbered in input order.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Cell {
+  /// `None` when several sites are equally near.
+  site_id: Option<usize>,
+  distance: f64,
+}
 
-  let mut duplicates = mark_duplicates(&new_ones);
+#[derive(Debug, PartialEq)]
+struct Region {
+  site_id: usize,
+  site: Point,
+  area: usize,
+  /// A region reaching the border of the bounding box keeps growing past it forever.
+  is_finite: bool,
+}
 
-  filled_ones.append(&mut duplicates);
-  filled_ones.append(&mut new_ones);
-  filled_ones.sort_by(|(_, _, a1, a2, _), (_, _, b1, b2, _)| {
-    let a = calc_nth(*a1, *a2);
-    let b = calc_nth(*b1, *b2);
-    a.cmp(&b)
-  });
-  filled_ones.dedup_by(|(_, _, a1, a2, _), (_, _, b1, b2, _)| calc_nth(*a1, *a2) == calc_nth(*b1, *b2));
-  filled_ones
+/// Marks every cell of the bounding box with its nearest site and the distance to it.
+fn fill_grid(coords: &[Point]) -> Grid<Cell> {
+  fill_grid_with(coords, &Manhattan)
 }
 
-fn fill_grid(coords: &Vec<(i32, i32)>) -> Vec<(bool, char, i32, i32, usize)> {
-  let mut res = position_to_grid_tuples(&coords);
-  let mut iter = 0;
+fn fill_grid_with(coords: &[Point], metric: &dyn Metric) -> Grid<Cell> {
+  fill_grid_within(coords, metric, calc_grid_size(coords))
+}
 
-  while iter < 160 {
-    res = fill_one_pass(res);
-    iter += 1;
-  }
-  res
+fn fill_grid_within(coords: &[Point], metric: &dyn Metric, bounds: Rect) -> Grid<Cell> {
+  Grid::from_fn(bounds, |point| {
+    let mut nearest = Cell { site_id: None, distance: f64::INFINITY };
+    for (site_id, &site) in coords.iter().enumerate() {
+      let distance = metric.distance(site, point);
+      if distance < nearest.distance {
+        nearest = Cell { site_id: Some(site_id), distance };
+      } else if distance == nearest.distance {
+        nearest.site_id = None;
+      }
+    }
+    nearest
+  })
 }
 
-fn find_biggest_area_which_is_not_expanding_anymore(grid: &[(bool, char, i32, i32, usize)]) -> usize {
-  let mut letter_counts = vec![];
-  for letter in CHARS.chars() {
-    letter_counts.push(count_letter(&grid, letter));
-  }
-  letter_counts.sort();
+/// The box every finite region of `metric` fits in, with room for one border cell of every
+/// infinite region. `None` when finite regions can reach arbitrarily far.
+fn region_bounds(coords: &[Point], metric: &dyn Metric) -> Option<Rect> {
+  let bounds = calc_grid_size(coords);
+  metric.finite_region_margin(bounds).map(|margin| bounds.expand(margin))
+}
 
-  let grid_after = fill_one_pass(grid.to_vec());
-  let mut letter_counts_after = vec![];
-  for letter in CHARS.chars() {
-    letter_counts_after.push(count_letter(&grid_after, letter));
+/// Areas of the nearest-site regions of a grid that `fill_grid` made from `coords`, one per site
+/// in site ID order. A site given twice ties with itself everywhere and gets an empty region.
+/// Finiteness is only right when the grid covers the `region_bounds` of its metric.
+fn find_regions(coords: &[Point], grid: &Grid<Cell>) -> Vec<Region> {
+  let bounds = grid.bounds();
+  let mut regions = coords.iter().enumerate()
+    .map(|(site_id, &site)| Region { site_id, site, area: 0, is_finite: true })
+    .collect::<Vec<Region>>();
+  for (point, cell) in grid.iter() {
+    let region = match cell.site_id.and_then(|site_id| regions.get_mut(site_id)) {
+      Some(region) => region,
+      None => continue,
+    };
+    region.area += 1;
+    if bounds.on_border(point) {
+      region.is_finite = false;
+    }
   }
-  letter_counts_after.sort();
+  regions
+}
 
-  letter_counts.iter()
-    .zip(letter_counts_after.iter())
-    .filter(|x| x.0 == x.1)
-    .map(|(a, _)| *a).last().expect("Boom")
+fn find_biggest_area_which_is_not_expanding_anymore(coords: &[Point], grid: &Grid<Cell>) -> usize {
+  find_regions(coords, grid).iter()
+    .filter(|region| region.is_finite)
+    .map(|region| region.area)
+    .max()
+    .expect("No finite region")
 }
 
 #[allow(dead_code)]
-fn print_grid(grid: &[(bool, char, i32, i32, usize)], grid_size: (i32, i32)) {
-  let extra = 1;
-  let default = (false, '_', 0, 0, 0);
-  for y in 0..(grid_size.1 + extra) {
-    for x in 0..=(grid_size.0 + extra) {
-      let (_, letter, _, _, _) = grid.iter()
-        .find(|(_, _, gx, gy, _)| *gx == x && *gy == y)
-        .unwrap_or(&default);
-      print!("{}", &letter);
-    }
-    println!();
+fn print_grid(grid: &Grid<Cell>) {
+  for row in grid.rows() {
+    let line = row.iter()
+      .map(|cell| cell.site_id.map_or('.', site_label))
+      .collect::<String>();
+    println!("{}", line);
   }
 }
 
-fn calc_grid_size(positions: &[(i32, i32)]) -> ((i32, i32), (i32, i32)) {
-  let (max_x, _) = positions.iter()
-    .max_by(|&(x1, _), &(x2, _)| x1.cmp(x2))
-    .unwrap_or_else(|| panic!("Max X find error"));
+fn calc_grid_size(positions: &[Point]) -> Rect {
+  Rect::bounding(positions.iter().cloned()).unwrap_or_else(|| panic!("Grid size error: no positions"))
+}
 
-  let (_, max_y) = positions.iter()
-    .max_by(|&(_, y1), &(_, y2)| y1.cmp(y2))
-    .unwrap_or_else(|| panic!("Max Y find error"));
+fn count_distance_sum(orig_positions: &[Point], pos: Point, metric: &dyn Metric) -> f64 {
+  orig_positions.iter().map(|&site| metric.distance(site, pos)).sum()
+}
 
-  let (min_x, _) = positions.iter()
-    .min_by(|&(x1, _), &(x2, _)| x1.cmp(x2))
-    .unwrap_or_else(|| panic!("Max Y find error"));
+fn find_region_with_all_locations(positions: &[Point], distance: i32) -> usize {
+  find_region_with_all_locations_with(positions, distance, &Manhattan)
+}
 
-  let (_, min_y) = positions.iter()
-    .min_by(|&(_, y1), &(_, y2)| y1.cmp(y2))
-    .unwrap_or_else(|| panic!("Max Y find error"));
+/// How far beyond the bounding box the region can reach. A cell `d` steps outside the box is at
+/// least `axis_lower_bound * d` away from every site, so its sum is at least `n` times that.
+fn safe_region_margin(sites: usize, distance: i32, metric: &dyn Metric) -> i32 {
+  if distance <= 0 {
+    return 0;
+  }
+  let step = sites as f64 * metric.axis_lower_bound();
+  if step <= 0.0 {
+    panic!("Distance sums do not grow away from the sites");
+  }
+  (f64::from(distance) / step).ceil() as i32
+}
 
-  ((*min_x, *min_y), (*max_x, *max_y))
+/// Sums of distances along one axis from each of `from..=to` to all `values`, using the
+/// number of values on either side of the position and their prefix sums.
+fn axis_distance_sums(values: &[i32], from: i32, to: i32) -> Vec<f64> {
+  let mut sorted = values.iter().map(|&value| i64::from(value)).collect::<Vec<i64>>();
+  sorted.sort_unstable();
+  let mut prefix = vec![0];
+  sorted.iter().for_each(|value| prefix.push(prefix[prefix.len() - 1] + value));
+  let total = prefix[sorted.len()];
+  (from..=to)
+    .map(|pos| {
+      let pos = i64::from(pos);
+      let below = sorted.partition_point(|&value| value <= pos);
+      let above = sorted.len() - below;
+      let sum = (pos * below as i64 - prefix[below]) + (total - prefix[below] - pos * above as i64);
+      sum as f64
+    })
+    .collect()
 }
 
-fn count_letter(grid: &[(bool, char, i32, i32, usize)], letter: char) -> usize {
-  let mut count = 0;
-  for &(_, sign, _, _, _) in grid.iter() {
-    if sign == letter {
-      count += 1;
+/// Whether the distance sum of each cell in `bounds` is below `distance`, for rendering.
+fn safe_cells(positions: &[Point], distance: i32, metric: &dyn Metric, bounds: Rect) -> Grid<bool> {
+  let limit = f64::from(distance);
+  match metric.axis_weights() {
+    Some((weight_x, weight_y)) => {
+      let xs = positions.iter().map(|site| site.x).collect::<Vec<i32>>();
+      let ys = positions.iter().map(|site| site.y).collect::<Vec<i32>>();
+      let sums_x = axis_distance_sums(&xs, bounds.min.x, bounds.max.x);
+      let sums_y = axis_distance_sums(&ys, bounds.min.y, bounds.max.y);
+      Grid::from_fn(bounds, |point| {
+        let (column, row) = ((point.x - bounds.min.x) as usize, (point.y - bounds.min.y) as usize);
+        weight_x * sums_x[column] + weight_y * sums_y[row] < limit
+      })
     }
+    None => Grid::from_fn(bounds, |point| count_distance_sum(positions, point, metric) < limit),
   }
-  count
 }
 
-fn count_distance_sum(orig_positions: &[(i32, i32)], pos: (i32, i32)) -> i32 {
-  orig_positions.iter().map(|(x, y)| distance((*x, *y), pos)).sum()
+/// Counts the cells of `bounds` whose distance sum is below `distance` without storing them.
+/// With per-axis weights each row needs the columns whose sum fits in what the row leaves over.
+fn count_safe_cells(positions: &[Point], distance: i32, metric: &dyn Metric, bounds: Rect) -> usize {
+  let limit = f64::from(distance);
+  match metric.axis_weights() {
+    Some((weight_x, weight_y)) => {
+      let xs = positions.iter().map(|site| site.x).collect::<Vec<i32>>();
+      let ys = positions.iter().map(|site| site.y).collect::<Vec<i32>>();
+      let mut sums_x = axis_distance_sums(&xs, bounds.min.x, bounds.max.x).into_iter()
+        .map(|sum| weight_x * sum)
+        .collect::<Vec<f64>>();
+      sums_x.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+      axis_distance_sums(&ys, bounds.min.y, bounds.max.y).iter()
+        .map(|&sum_y| sums_x.partition_point(|&sum_x| sum_x < limit - weight_y * sum_y))
+        .sum()
+    }
+    None => bounds.points().filter(|&point| count_distance_sum(positions, point, metric) < limit).count(),
+  }
 }
 
-fn distance(a: (i32, i32), b: (i32, i32)) -> i32 {
-  (a.0 - b.0).abs() + (a.1 - b.1).abs()
+fn find_region_with_all_locations_with(positions: &[Point], distance: i32, metric: &dyn Metric) -> usize {
+  let margin = safe_region_margin(positions.len(), distance, metric);
+  count_safe_cells(positions, distance, metric, calc_grid_size(positions).expand(margin))
 }
 
-fn find_region_with_all_locations(positions: &Vec<(i32, i32)>, distance: i32) -> usize {
-  let ((min_x, min_y), (max_x, max_y)) = calc_grid_size(positions);
-  let mut area_sizes = vec![];
-  for x in min_x..max_x {
-    for y in min_y..max_y {
-      let dist_sum = count_distance_sum(&positions, (x,y));
-      if dist_sum < distance {
-        area_sizes.push(dist_sum);
-      }
+fn arg_value(name: &str) -> Option<String> {
+  env::args()
+    .skip_while(|arg| arg != name)
+    .nth(1)
+}
+
+fn arg_numbers(name: &str) -> Option<Vec<i32>> {
+  arg_value(name).map(|value| {
+    value.split(',')
+      .map(|x| x.parse::<i32>().unwrap_or_else(|_| panic!("Number cast error {}", x)))
+      .collect::<Vec<i32>>()
+  })
+}
+
+/// `--nearest x,y,k`, `--within x,y,r` and `--largest-empty` queries over the sites.
+fn print_queries(positions: &[Point]) {
+  let metric = metric_by_name(&arg_value("--metric").unwrap_or_else(|| "manhattan".to_string()));
+  let index = SiteIndex::new(positions);
+  let print_sites = |sites: Vec<(usize, f64)>| sites.iter().for_each(|&(site_id, distance)| {
+    let site = positions[site_id];
+    println!("  {} ({}, {}) => {}", site_tag(site_id), site.x, site.y, distance);
+  });
+  if let Some(nums) = arg_numbers("--nearest") {
+    let k = nums.get(2).map_or(1, |&k| k as usize);
+    print_sites(index.k_nearest(Point::new(nums[0], nums[1]), k, metric.as_ref()));
+  }
+  if let Some(nums) = arg_numbers("--within") {
+    print_sites(index.within(Point::new(nums[0], nums[1]), f64::from(nums[2]), metric.as_ref()));
+  }
+  if env::args().any(|arg| arg == "--largest-empty") {
+    if let Some((centre, half_side)) = index.largest_empty_square() {
+      println!("Largest empty square: ({}, {}) half side {}", centre.x, centre.y, half_side);
+    }
+    if let Some((centre, radius)) = index.largest_empty_diamond() {
+      println!("Largest empty diamond: ({}, {}) radius {}", centre.x, centre.y, radius);
     }
   }
-  area_sizes.len()
 }
 
 fn main() {
   let data = read_input_file("./input.txt");
   let mut lines = lines_to_vec(&data);
   let positions = line_to_positions(&mut lines);
-  let areas = fill_grid(&positions);
-  println!("Part one: {:?}", find_biggest_area_which_is_not_expanding_anymore(&areas));
+  let metric_name = arg_value("--metric");
+  let (areas, bounded, res_b) = match metric_name.as_deref() {
+    Some(name) => {
+      let metric = metric_by_name(name);
+      let bounds = region_bounds(&positions, metric.as_ref());
+      (fill_grid_within(&positions, metric.as_ref(), bounds.unwrap_or_else(|| calc_grid_size(&positions))),
+       bounds.is_some(),
+       find_region_with_all_locations_with(&positions, 10_000, metric.as_ref()))
+    }
+    None => (fill_grid(&positions), true, find_region_with_all_locations(&positions, 10_000)),
+  };
+
+  if let Some(format) = arg_value("--render") {
+    let metric = metric_by_name(&arg_value("--metric").unwrap_or_else(|| "manhattan".to_string()));
+    let safe = safe_cells(&positions, 10_000, metric.as_ref(), areas.bounds());
+    let regions = find_regions(&positions, &areas);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = match format.as_str() {
+      "ppm" => write_ppm(&rasterize(&areas, &regions, bounded, &safe, RENDER_SCALE), &mut out),
+      #[cfg(feature = "png")]
+      "png" => render::write_png(&rasterize(&areas, &regions, bounded, &safe, RENDER_SCALE), &mut out),
+      "svg" => io::Write::write_all(&mut out, render_svg(&areas, &regions, bounded, &safe).as_bytes()),
+      other => panic!("Unknown render format {}", other),
+    };
+    written.unwrap_or_else(|e| panic!("Render write error: {}", e));
+    return;
+  }
+
+  if bounded {
+    println!("Part one: {:?}", find_biggest_area_which_is_not_expanding_anymore(&positions, &areas));
+  } else {
+    println!("Part one: finite {} regions can reach arbitrarily far from the sites", metric_name.unwrap_or_default());
+  }
+  if env::args().any(|arg| arg == "--regions") {
+    find_regions(&positions, &areas).iter().for_each(|region| {
+      let extent = match (bounded, region.is_finite) {
+        (false, _) => "within the bounding box",
+        (true, true) => "finite",
+        (true, false) => "infinite",
+      };
+      println!("  {} ({}, {}) => {} ({})", site_tag(region.site_id), region.site.x, region.site.y, region.area, extent);
+    });
+  }
 
-  let res_b = find_region_with_all_locations(&positions, 10_000);
   println!("Part two: {:?}", res_b);
+  print_queries(&positions);
+}
+
+#[allow(dead_code)]
+fn points(coords: &[(i32, i32)]) -> Vec<Point> {
+  coords.iter().map(|&coord| Point::from(coord)).collect()
 }
 
 #[test]
@@ -237,8 +350,99 @@ bbb.ffffFf
 fn basic_test() {
   let mut lines = lines_to_vec("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
   let positions = line_to_positions(&mut lines);
-  let grid_size = calc_grid_size(&positions);
   let areas = fill_grid(&positions);
-  print_grid(&areas, grid_size.1);
-  assert_eq!(find_biggest_area_which_is_not_expanding_anymore(&areas), 17)
+  print_grid(&areas);
+  assert_eq!(find_biggest_area_which_is_not_expanding_anymore(&positions, &areas), 17)
+}
+
+#[test]
+fn regions_test() {
+  let mut lines = lines_to_vec("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+  let positions = line_to_positions(&mut lines);
+  let regions = find_regions(&positions, &fill_grid(&positions));
+  let finite = regions.iter()
+    .filter(|region| region.is_finite)
+    .map(|region| (region.site_id, region.site, region.area))
+    .collect::<Vec<_>>();
+  assert_eq!(finite, vec![(3, Point::new(3, 4), 9), (4, Point::new(5, 5), 17)]);
+  assert_eq!(regions.len(), 6);
+
+  let twice = points(&[(0, 0), (2, 2), (2, 2), (4, 0)]);
+  let regions = find_regions(&twice, &fill_grid(&twice));
+  let areas = regions.iter().map(|region| (region.site, region.area)).collect::<Vec<_>>();
+  assert_eq!(areas, vec![(Point::new(0, 0), 3), (Point::new(2, 2), 0), (Point::new(2, 2), 0), (Point::new(4, 0), 3)]);
+}
+
+#[test]
+fn many_sites_test() {
+  let positions = (0..60).map(|idx| Point::new(idx * 3, (idx * 7) % 50)).collect::<Vec<Point>>();
+  let regions = find_regions(&positions, &fill_grid(&positions));
+  assert_eq!(regions.len(), 60);
+  assert_eq!(regions[59].site, Point::new(177, 13));
+  assert_eq!((site_label(0), site_label(52), site_label(53)), ('A', 'A', 'B'));
+  assert_eq!([site_tag(0), site_tag(51), site_tag(52), site_tag(53), site_tag(52 * 53)],
+             ["A", "z", "AA", "AB", "AAA"]);
+}
+
+#[test]
+fn metrics_test() {
+  let positions = points(&[(0, 0), (4, 1)]);
+  let chebyshev = fill_grid_with(&positions, &metric::Chebyshev);
+  let euclidean = fill_grid_with(&positions, &metric::Euclidean);
+  let ids = |grid: &Grid<Cell>| grid.values().map(|cell| cell.site_id).collect::<Vec<_>>();
+  assert_eq!(ids(&chebyshev), vec![Some(0), Some(0), None, Some(1), Some(1),
+                                   Some(0), Some(0), None, Some(1), Some(1)]);
+  assert_eq!(ids(&euclidean), vec![Some(0), Some(0), Some(0), Some(1), Some(1),
+                                   Some(0), Some(0), Some(1), Some(1), Some(1)]);
+  let equidistant = points(&[(260, 203), (328, 35)]);
+  let origin = Rect::new(Point::new(0, 0), Point::new(0, 0));
+  assert_eq!(fill_grid_within(&equidistant, &metric::Euclidean, origin)[Point::new(0, 0)].site_id, None);
+  let weighted = metric::WeightedManhattan { x: 1.0, y: 10.0 };
+  assert_eq!(find_region_with_all_locations_with(&points(&[(0, 0), (2, 2)]), 5, &Manhattan), 9);
+  assert_eq!(find_region_with_all_locations_with(&points(&[(0, 0), (2, 2)]), 22, &weighted), 0);
+  assert_eq!(find_region_with_all_locations_with(&points(&[(0, 0), (2, 2)]), 23, &weighted), 9);
+}
+
+#[test]
+fn region_beyond_bounding_box_test() {
+  assert_eq!(find_region_with_all_locations(&points(&[(0, 0)]), 3), 13);
+  assert_eq!(find_region_with_all_locations(&points(&[(0, 0), (1, 0)]), 4), 8);
+  assert_eq!(find_region_with_all_locations(&points(&[(0, 0)]), 10_000), 2 * 9999 * 9999 + 2 * 9999 + 1);
+
+  struct Opaque;
+  impl Metric for Opaque {
+    fn distance(&self, a: Point, b: Point) -> f64 {
+      Manhattan.distance(a, b)
+    }
+  }
+  let mut lines = lines_to_vec("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+  let positions = line_to_positions(&mut lines);
+  for &distance in [32, 100, 250].iter() {
+    assert_eq!(find_region_with_all_locations(&positions, distance),
+               find_region_with_all_locations_with(&positions, distance, &Opaque));
+  }
+}
+
+#[test]
+fn finite_regions_by_metric_test() {
+  // Reference: a region is infinite when it owns a cell on the border of a much larger box.
+  let finite = |positions: &[Point], metric: &dyn Metric, bounds: Rect| {
+    find_regions(positions, &fill_grid_within(positions, metric, bounds)).into_iter()
+      .filter(|region| region.is_finite)
+      .map(|region| (region.site_id, region.area))
+      .collect::<Vec<_>>()
+  };
+  let mut seed = 7u32;
+  let mut next = || {
+    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    (seed >> 16) as i32 % 16
+  };
+  for _ in 0..12 {
+    let positions = (0..8).map(|_| Point::new(next(), next())).collect::<Vec<Point>>();
+    for metric in [&Manhattan as &dyn Metric, &metric::Chebyshev].iter() {
+      let reference = finite(&positions, *metric, calc_grid_size(&positions).expand(60));
+      assert_eq!(finite(&positions, *metric, region_bounds(&positions, *metric).unwrap()), reference);
+    }
+  }
+  assert_eq!(region_bounds(&points(&[(0, 0), (2, 2)]), &metric::Euclidean), None);
 }
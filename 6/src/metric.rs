@@ -0,0 +1,134 @@
+use geometry::{Point, Rect};
+
+pub trait Metric {
+  fn distance(&self, a: Point, b: Point) -> f64;
+
+  /// Weights `(wx, wy)` when the distance is `wx * |dx| + wy * |dy|`, which lets sums of
+  /// distances be computed one axis at a time.
+  fn axis_weights(&self) -> Option<(f64, f64)> {
+    None
+  }
+
+  /// A factor `c` with `distance >= c * max(|dx|, |dy|)`.
+  fn axis_lower_bound(&self) -> f64 {
+    1.0
+  }
+
+  /// How far past `bounds`, the bounding box of the sites, a finite region can reach. A region
+  /// owning a cell on the border of the grown box keeps growing forever. `None` when finite
+  /// regions have no such bound.
+  fn finite_region_margin(&self, _bounds: Rect) -> Option<i32> {
+    None
+  }
+}
+
+/// Taxicab distance, as in the puzzle.
+pub struct Manhattan;
+
+/// The larger of the two axis distances, i.e. king moves on a chess board.
+pub struct Chebyshev;
+
+/// Straight line distance. Computed as a plain square root rather than `hypot`, which is not
+/// correctly rounded: cells whose squared distances tie must get exactly equal distances.
+pub struct Euclidean;
+
+/// Taxicab distance where moving along each axis has its own cost.
+pub struct WeightedManhattan {
+  pub x: f64,
+  pub y: f64,
+}
+
+fn axis_distances(a: Point, b: Point) -> (f64, f64) {
+  (f64::from((a.x - b.x).abs()), f64::from((a.y - b.y).abs()))
+}
+
+impl Metric for Manhattan {
+  fn distance(&self, a: Point, b: Point) -> f64 {
+    let (dx, dy) = axis_distances(a, b);
+    dx + dy
+  }
+
+  fn axis_weights(&self) -> Option<(f64, f64)> {
+    Some((1.0, 1.0))
+  }
+
+  /// Past the bounding box every distance grows by the same step, so whoever owns a border
+  /// cell owns everything beyond it.
+  fn finite_region_margin(&self, _bounds: Rect) -> Option<i32> {
+    Some(0)
+  }
+}
+
+impl Metric for Chebyshev {
+  fn distance(&self, a: Point, b: Point) -> f64 {
+    let (dx, dy) = axis_distances(a, b);
+    dx.max(dy)
+  }
+
+  /// Chebyshev distance is taxicab distance on the diagonals `x + y` and `x - y`, so finite
+  /// regions stay inside the bounding box of the sites in those coordinates. That box reaches
+  /// at most half the larger side past the usual one.
+  fn finite_region_margin(&self, bounds: Rect) -> Option<i32> {
+    Some(bounds.width().max(bounds.height()) as i32 / 2 + 1)
+  }
+}
+
+impl Metric for Euclidean {
+  fn distance(&self, a: Point, b: Point) -> f64 {
+    let (dx, dy) = axis_distances(a, b);
+    (dx * dx + dy * dy).sqrt()
+  }
+}
+
+impl Metric for WeightedManhattan {
+  fn distance(&self, a: Point, b: Point) -> f64 {
+    let (dx, dy) = axis_distances(a, b);
+    self.x * dx + self.y * dy
+  }
+
+  fn axis_weights(&self) -> Option<(f64, f64)> {
+    Some((self.x, self.y))
+  }
+
+  fn axis_lower_bound(&self) -> f64 {
+    self.x.min(self.y)
+  }
+
+  fn finite_region_margin(&self, _bounds: Rect) -> Option<i32> {
+    Some(0)
+  }
+}
+
+/// `manhattan`, `chebyshev`, `euclidean` or `weighted:X,Y` with the axis weights.
+pub fn metric_by_name(name: &str) -> Box<dyn Metric> {
+  match name {
+    "manhattan" => Box::new(Manhattan),
+    "chebyshev" => Box::new(Chebyshev),
+    "euclidean" => Box::new(Euclidean),
+    weighted if weighted.starts_with("weighted:") => {
+      let weights = weighted["weighted:".len()..].split(',')
+        .map(|x| x.parse::<f64>().unwrap_or_else(|_| panic!("Weight cast error {}", x)))
+        .collect::<Vec<f64>>();
+      match weights.as_slice() {
+        &[x, y] => Box::new(WeightedManhattan { x, y }),
+        _ => panic!("Expected two weights in {}", weighted),
+      }
+    }
+    other => panic!("Unknown metric {}", other),
+  }
+}
+
+#[test]
+fn metric_test() {
+  let (a, b) = (Point::new(1, 1), Point::new(4, -3));
+  assert_eq!(Manhattan.distance(a, b), 7.0);
+  assert_eq!(Chebyshev.distance(a, b), 4.0);
+  assert_eq!(Euclidean.distance(a, b), 5.0);
+  assert_eq!(WeightedManhattan { x: 2.0, y: 0.5 }.distance(a, b), 8.0);
+  assert_eq!(metric_by_name("weighted:2,0.5").distance(a, b), 8.0);
+  assert_eq!(metric_by_name("weighted:2,0.5").axis_lower_bound(), 0.5);
+  assert_eq!(Euclidean.axis_weights(), None);
+  let bounds = Rect::new(Point::new(0, 0), Point::new(9, 4));
+  assert_eq!((Manhattan.finite_region_margin(bounds), Chebyshev.finite_region_margin(bounds)), (Some(0), Some(6)));
+  assert_eq!(Euclidean.finite_region_margin(bounds), None);
+}
@@ -0,0 +1,171 @@
+use crate::{Cell, Region};
+use geometry::{Grid, Point};
+use std::io::{self, Write};
+
+const TIE: [u8; 3] = [160, 160, 160];
+const SITE: [u8; 3] = [0, 0, 0];
+const HATCH_PERIOD: usize = 6;
+const SAFE_OPACITY: f64 = 0.45;
+
+pub struct Raster {
+  pub width: usize,
+  pub height: usize,
+  /// RGB triples row by row.
+  pub pixels: Vec<u8>,
+}
+
+/// Spreads site colours around the hue circle by the golden angle so neighbouring IDs differ.
+fn site_colour(site_id: usize) -> [u8; 3] {
+  let hue = (site_id as f64 * 137.508) % 360.0 / 60.0;
+  let (saturation, value) = (0.55, 0.95);
+  let chroma = value * saturation;
+  let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+  let (r, g, b) = match hue as u32 {
+    0 => (chroma, second, 0.0),
+    1 => (second, chroma, 0.0),
+    2 => (0.0, chroma, second),
+    3 => (0.0, second, chroma),
+    4 => (second, 0.0, chroma),
+    _ => (chroma, 0.0, second),
+  };
+  let channel = |c: f64| ((c + value - chroma) * 255.0).round() as u8;
+  [channel(r), channel(g), channel(b)]
+}
+
+fn hex(colour: [u8; 3]) -> String {
+  format!("#{:02x}{:02x}{:02x}", colour[0], colour[1], colour[2])
+}
+
+fn blend(colour: [u8; 3], with: [u8; 3], amount: f64) -> [u8; 3] {
+  let mix = |a: u8, b: u8| (f64::from(a) * (1.0 - amount) + f64::from(b) * amount).round() as u8;
+  [mix(colour[0], with[0]), mix(colour[1], with[1]), mix(colour[2], with[2])]
+}
+
+/// Only a grid that covers the `region_bounds` of its metric, i.e. is `bounded`, tells finite
+/// regions from infinite ones. Otherwise no region is shown as infinite.
+fn is_infinite(regions: &[Region], site_id: usize, bounded: bool) -> bool {
+  bounded && regions.binary_search_by_key(&site_id, |region| region.site_id)
+    .map(|idx| !regions[idx].is_finite)
+    .unwrap_or(false)
+}
+
+/// Cell colour before hatching: the site's colour, grey for ties and black for the sites.
+fn cell_colour(cell: &Cell) -> [u8; 3] {
+  match (cell.site_id, cell.distance) {
+    (Some(_), 0.0) => SITE,
+    (Some(site_id), _) => site_colour(site_id),
+    (None, _) => TIE,
+  }
+}
+
+/// Draws each cell as a `scale` × `scale` block. Infinite regions of a `bounded` grid get diagonal
+/// hatching and the safe region is lightened.
+pub fn rasterize(grid: &Grid<Cell>, regions: &[Region], bounded: bool, safe: &Grid<bool>, scale: usize) -> Raster {
+  let bounds = grid.bounds();
+  let scale = scale.max(1);
+  let (width, height) = (bounds.width() * scale, bounds.height() * scale);
+  let mut pixels = vec![0; width * height * 3];
+  for (point, cell) in grid.iter() {
+    let colour = cell_colour(cell);
+    let hatched = cell.site_id.is_some_and(|site_id| is_infinite(regions, site_id, bounded));
+    let is_safe = safe.get(point) == Some(&true);
+    let (column, row) = ((point.x - bounds.min.x) as usize, (point.y - bounds.min.y) as usize);
+    for py in row * scale..(row + 1) * scale {
+      for px in column * scale..(column + 1) * scale {
+        let mut pixel = colour;
+        if hatched && (px + py) % HATCH_PERIOD < 2 {
+          pixel = blend(pixel, SITE, 0.35);
+        }
+        if is_safe {
+          pixel = blend(pixel, [255, 255, 255], SAFE_OPACITY);
+        }
+        let offset = (py * width + px) * 3;
+        pixels[offset..offset + 3].copy_from_slice(&pixel);
+      }
+    }
+  }
+  Raster { width, height, pixels }
+}
+
+/// Binary PPM (P6), readable by most image tools without any dependencies.
+pub fn write_ppm<W: Write>(raster: &Raster, out: &mut W) -> io::Result<()> {
+  write!(out, "P6\n{} {}\n255\n", raster.width, raster.height)?;
+  out.write_all(&raster.pixels)
+}
+
+#[cfg(feature = "png")]
+pub fn write_png<W: Write>(raster: &Raster, out: &mut W) -> io::Result<()> {
+  let mut encoder = png::Encoder::new(out, raster.width as u32, raster.height as u32);
+  encoder.set_color(png::ColorType::Rgb);
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut writer = encoder.write_header().map_err(io::Error::from)?;
+  writer.write_image_data(&raster.pixels).map_err(io::Error::from)
+}
+
+/// One cell per SVG unit. Neighbouring cells of the same colour in a row share a rectangle.
+pub fn render_svg(grid: &Grid<Cell>, regions: &[Region], bounded: bool, safe: &Grid<bool>) -> String {
+  let bounds = grid.bounds();
+  let mut cells = String::new();
+  let mut hatches = String::new();
+  let mut overlay = String::new();
+  for (row, row_cells) in grid.rows().enumerate() {
+    let mut runs: Vec<(usize, usize, [u8; 3], bool, bool)> = vec![];
+    for (column, cell) in row_cells.iter().enumerate() {
+      let colour = cell_colour(cell);
+      let hatched = cell.site_id.is_some_and(|site_id| is_infinite(regions, site_id, bounded));
+      let point = Point::new(bounds.min.x + column as i32, bounds.min.y + row as i32);
+      let is_safe = safe.get(point) == Some(&true);
+      match runs.last_mut() {
+        Some(run) if (run.2, run.3, run.4) == (colour, hatched, is_safe) => run.1 += 1,
+        _ => runs.push((column, 1, colour, hatched, is_safe)),
+      }
+    }
+    for &(column, len, colour, hatched, is_safe) in runs.iter() {
+      let rect = format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\"", column, row, len);
+      cells.push_str(&format!("  {} fill=\"{}\"/>\n", rect, hex(colour)));
+      if hatched {
+        hatches.push_str(&format!("  {} fill=\"url(#hatch)\"/>\n", rect));
+      }
+      if is_safe {
+        overlay.push_str(&format!("  {} fill=\"#ffffff\" fill-opacity=\"{}\"/>\n", rect, SAFE_OPACITY));
+      }
+    }
+  }
+  format!("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n\
+           <defs><pattern id=\"hatch\" width=\"2\" height=\"2\" patternUnits=\"userSpaceOnUse\" \
+           patternTransform=\"rotate(45)\"><rect width=\"0.5\" height=\"2\" fill=\"#000000\" fill-opacity=\"0.35\"/>\
+           </pattern></defs>\n{}{}{}</svg>\n",
+          bounds.width(), bounds.height(), cells, hatches, overlay)
+}
+
+#[test]
+fn raster_test() {
+  let positions = crate::points(&[(0, 0), (2, 0)]);
+  let grid = crate::fill_grid(&positions);
+  let regions = crate::find_regions(&positions, &grid);
+  let safe = Grid::from_fn(grid.bounds(), |point| point.x == 1);
+  let raster = rasterize(&grid, &regions, true, &safe, 2);
+  assert_eq!((raster.width, raster.height), (6, 2));
+  assert_eq!(&raster.pixels[0..3], &SITE);
+  assert_eq!(&raster.pixels[6..9], &blend(TIE, [255, 255, 255], SAFE_OPACITY));
+
+  let mut ppm = vec![];
+  write_ppm(&raster, &mut ppm).unwrap();
+  assert!(ppm.starts_with(b"P6\n6 2\n255\n"));
+  assert_eq!(ppm.len(), 11 + 6 * 2 * 3);
+}
+
+#[test]
+fn svg_test() {
+  let positions = crate::points(&[(0, 0), (3, 0), (0, 1)]);
+  let grid = crate::fill_grid(&positions);
+  let regions = crate::find_regions(&positions, &grid);
+  let svg = render_svg(&grid, &regions, true, &Grid::new(grid.bounds(), false));
+  assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 4 2\""));
+  assert!(svg.contains("<rect x=\"1\" y=\"0\" width=\"1\" height=\"1\" fill=\"#f26d6d\"/>"));
+  assert!(svg.contains("<rect x=\"2\" y=\"1\" width=\"1\" height=\"1\" fill=\"#a0a0a0\"/>"));
+  assert_eq!(svg.matches("fill=\"url(#hatch)\"").count(), 7);
+  assert!(!svg.contains("fill-opacity=\"0.45\""));
+  let unbounded = render_svg(&grid, &regions, false, &Grid::new(grid.bounds(), false));
+  assert_eq!(unbounded.matches("fill=\"url(#hatch)\"").count(), 0);
+}
@@ -0,0 +1,162 @@
+use crate::metric::{Chebyshev, Manhattan, Metric};
+use geometry::{Point, Rect};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Sites bucketed into square cells so that queries only look at the cells around a point.
+pub struct SiteIndex {
+  sites: Vec<Point>,
+  bounds: Rect,
+  cell_size: i32,
+  buckets: HashMap<Point, Vec<usize>>,
+}
+
+fn by_distance(a: &(usize, f64), b: &(usize, f64)) -> Ordering {
+  a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0))
+}
+
+impl SiteIndex {
+  /// Picks a cell size that puts about one site in each bucket.
+  pub fn new(sites: &[Point]) -> Self {
+    let bounds = Rect::bounding(sites.iter().cloned()).unwrap_or_else(|| panic!("No sites to index"));
+    let cell_size = ((bounds.area() as f64 / sites.len() as f64).sqrt().ceil() as i32).max(1);
+    SiteIndex::with_cell_size(sites, cell_size)
+  }
+
+  pub fn with_cell_size(sites: &[Point], cell_size: i32) -> Self {
+    let bounds = Rect::bounding(sites.iter().cloned()).unwrap_or_else(|| panic!("No sites to index"));
+    let mut index = SiteIndex { sites: sites.to_vec(), bounds, cell_size: cell_size.max(1), buckets: HashMap::new() };
+    for (site_id, &site) in sites.iter().enumerate() {
+      let bucket = index.bucket_of(site);
+      index.buckets.entry(bucket).or_default().push(site_id);
+    }
+    index
+  }
+
+  fn bucket_of(&self, point: Point) -> Point {
+    Point::new((point.x - self.bounds.min.x).div_euclid(self.cell_size),
+               (point.y - self.bounds.min.y).div_euclid(self.cell_size))
+  }
+
+  /// The ring beyond which no bucket holds any site.
+  fn last_ring(&self, centre: Point) -> i32 {
+    let last = self.bucket_of(self.bounds.max);
+    centre.x.abs().max((last.x - centre.x).abs()).max(centre.y.abs()).max((last.y - centre.y).abs())
+  }
+
+  /// Buckets at Chebyshev distance `ring` from `centre`.
+  fn ring(centre: Point, ring: i32) -> Vec<Point> {
+    if ring == 0 {
+      return vec![centre];
+    }
+    let (min, max) = (Point::new(centre.x - ring, centre.y - ring), Point::new(centre.x + ring, centre.y + ring));
+    Rect::new(min, max).points()
+      .filter(|bucket| bucket.x == min.x || bucket.x == max.x || bucket.y == min.y || bucket.y == max.y)
+      .collect()
+  }
+
+  /// A lower bound for the distance from a point to any site in a bucket `ring` rings away.
+  fn ring_distance(&self, ring: i32, metric: &dyn Metric) -> f64 {
+    if ring == 0 { 0.0 } else { metric.axis_lower_bound() * f64::from((ring - 1) * self.cell_size + 1) }
+  }
+
+  fn visit(&self, point: Point, ring: i32, metric: &dyn Metric, found: &mut Vec<(usize, f64)>) {
+    for bucket in SiteIndex::ring(self.bucket_of(point), ring) {
+      if let Some(site_ids) = self.buckets.get(&bucket) {
+        found.extend(site_ids.iter().map(|&site_id| (site_id, metric.distance(self.sites[site_id], point))));
+      }
+    }
+  }
+
+  /// The `k` sites nearest to `point` with their distances, nearest first and lower IDs first on ties.
+  pub fn k_nearest(&self, point: Point, k: usize, metric: &dyn Metric) -> Vec<(usize, f64)> {
+    let mut found = vec![];
+    if k == 0 {
+      return found;
+    }
+    for ring in 0..=self.last_ring(self.bucket_of(point)) {
+      if found.len() == k && found[k - 1].1 < self.ring_distance(ring, metric) {
+        break;
+      }
+      self.visit(point, ring, metric, &mut found);
+      found.sort_by(by_distance);
+      found.truncate(k);
+    }
+    found
+  }
+
+  /// Every site at most `radius` away from `point`, nearest first.
+  pub fn within(&self, point: Point, radius: f64, metric: &dyn Metric) -> Vec<(usize, f64)> {
+    let mut found = vec![];
+    for ring in 0..=self.last_ring(self.bucket_of(point)) {
+      if self.ring_distance(ring, metric) > radius {
+        break;
+      }
+      self.visit(point, ring, metric, &mut found);
+    }
+    found.retain(|&(_, distance)| distance <= radius);
+    found.sort_by(by_distance);
+    found
+  }
+
+  /// The centre and radius of the largest ball of `metric` inside the bounding box of the sites
+  /// that contains no site. The first centre in row order wins ties.
+  fn largest_empty(&self, metric: &dyn Metric) -> Option<(Point, i32)> {
+    let bounds = self.bounds;
+    bounds.points()
+      .filter_map(|centre| {
+        let nearest = self.k_nearest(centre, 1, metric).first()?.1;
+        let to_edge = (centre.x - bounds.min.x).min(bounds.max.x - centre.x)
+          .min(centre.y - bounds.min.y).min(bounds.max.y - centre.y);
+        let radius = (nearest.ceil() as i32 - 1).min(to_edge);
+        if radius >= 0 { Some((centre, radius)) } else { None }
+      })
+      .fold(None, |best: Option<(Point, i32)>, candidate| match best {
+        Some(best) if best.1 >= candidate.1 => Some(best),
+        _ => Some(candidate),
+      })
+  }
+
+  /// The largest axis-aligned square free of sites, as its centre and half side.
+  pub fn largest_empty_square(&self) -> Option<(Point, i32)> {
+    self.largest_empty(&Chebyshev)
+  }
+
+  /// The largest diamond free of sites, as its centre and radius.
+  pub fn largest_empty_diamond(&self) -> Option<(Point, i32)> {
+    self.largest_empty(&Manhattan)
+  }
+}
+
+#[allow(dead_code)]
+fn brute_force(sites: &[Point], point: Point, metric: &dyn Metric) -> Vec<(usize, f64)> {
+  let mut all = sites.iter().enumerate().map(|(site_id, &site)| (site_id, metric.distance(site, point))).collect::<Vec<_>>();
+  all.sort_by(by_distance);
+  all
+}
+
+#[test]
+fn nearest_test() {
+  let sites = (0..200).map(|idx| Point::new((idx * 37) % 101, (idx * 53) % 89 - 40)).collect::<Vec<Point>>();
+  let metrics: [&dyn Metric; 3] = [&Manhattan, &crate::metric::Euclidean, &crate::metric::WeightedManhattan { x: 1.0, y: 3.0 }];
+  for &cell_size in [1, 7, 40].iter() {
+    let index = SiteIndex::with_cell_size(&sites, cell_size);
+    for &point in [Point::new(50, 0), Point::new(-30, 80), Point::new(3, -39)].iter() {
+      for &metric in metrics.iter() {
+        let expected = brute_force(&sites, point, metric);
+        assert_eq!(index.k_nearest(point, 5, metric), expected[..5].to_vec());
+        let within = expected.iter().cloned().filter(|&(_, distance)| distance <= 20.0).collect::<Vec<_>>();
+        assert_eq!(index.within(point, 20.0, metric), within);
+      }
+    }
+  }
+}
+
+#[test]
+fn largest_empty_test() {
+  let sites = crate::points(&[(1, 1), (1, 6), (8, 3), (3, 4), (5, 5), (8, 9)]);
+  let index = SiteIndex::new(&sites);
+  assert_eq!(index.k_nearest(Point::new(4, 4), 2, &Manhattan), vec![(3, 1.0), (4, 2.0)]);
+  assert_eq!(index.largest_empty_square(), Some((Point::new(3, 2), 1)));
+  assert_eq!(index.largest_empty_diamond(), Some((Point::new(3, 7), 2)));
+}
@@ -1,109 +1,219 @@
+mod parse;
+mod queue;
+mod schedule;
+
+use crate::parse::{format_by_name, parse_rules, Format, Rule};
+use crate::queue::{ReadyQueue, TieBreak};
+use crate::schedule::schedule;
 use std::collections::HashMap;
-use std::{fs::File, io::Read, path::Path};
+use std::{env, fs::File, io::Read, path::Path};
 use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
 
 #[derive(Debug)]
-struct Rules {
-  before: HashSet<char>,
-  after: HashSet<char>,
+struct Rules<N> {
+  before: HashSet<N>,
+  after: HashSet<N>,
+  /// Position of the step's first appearance in the rules.
+  order: usize,
+}
+
+/// The rules can't all be satisfied. `cycle` starts and ends with the same step and
+/// `unavailable` holds the steps that are not on it but wait for it.
+#[derive(Debug, PartialEq)]
+struct CycleError<N> {
+  cycle: Vec<N>,
+  unavailable: Vec<N>,
+}
+
+impl<N: fmt::Display> fmt::Display for CycleError<N> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let cycle = self.cycle.iter().map(|step| step.to_string()).collect::<Vec<_>>();
+    write!(f, "steps form a cycle {}", cycle.join(" -> "))?;
+    for step in self.unavailable.iter() {
+      write!(f, "\nwarning: step {} never becomes available", step)?;
+    }
+    Ok(())
+  }
 }
 
 fn read_input_file(filename: &str) -> String {
-  let mut file = File::open(&Path::new(filename))
+  let mut file = File::open(Path::new(filename))
     .unwrap_or_else(|e| panic!("File {} read error: {}", filename, e));
   let mut buf = String::new();
   file.read_to_string(&mut buf).unwrap_or_else(|e| panic!("Buffered read error: {}", e));
   buf
 }
 
-fn lines_to_vec(input: &str) -> Vec<&str> {
-  input.lines()
-    .filter(|e| !e.is_empty())
-    .collect::<Vec<&str>>()
+fn arg_value(name: &str) -> Option<String> {
+  env::args()
+    .skip_while(|arg| arg != name)
+    .nth(1)
 }
 
-fn parse_input_line(line: &str) -> Vec<char> {
-  line.split_whitespace()
-    .filter(|&el| el.chars().count() == 1 && el.chars().all(|e| e.is_uppercase()))
-    .flat_map(|el| el.to_string().chars().collect::<Vec<char>>())
-    .collect::<Vec<char>>()
+fn arg_number(name: &str, default: u32) -> u32 {
+  arg_value(name)
+    .map(|value| value.parse::<u32>().unwrap_or_else(|_| panic!("Number cast error {}", value)))
+    .unwrap_or(default)
 }
 
-fn construct_rule_map(characters: &[Vec<char>]) -> HashMap<char, Rules> {
-  let mut character_map = HashMap::new();
+/// Each rule is `[before, after]`, or `[step]` for a step without dependencies.
+fn construct_rule_map<N: Clone + Eq + Hash>(steps: &[Vec<N>]) -> HashMap<N, Rules<N>> {
+  let mut step_map: HashMap<N, Rules<N>> = HashMap::new();
+  let add_step = |step_map: &mut HashMap<N, Rules<N>>, step: &N| {
+    let order = step_map.len();
+    step_map.entry(step.clone()).or_insert(Rules { before: HashSet::new(), after: HashSet::new(), order });
+  };
 
-  characters.iter().for_each(|letters| {
-    let mut before = HashSet::new();
-    let mut after = HashSet::new();
-    before.insert(letters[0]);
-    after.insert(letters[1]);
-    character_map.entry(letters[0])
-      .and_modify(|set: &mut Rules| { set.after.insert(letters[1]); })
-      .or_insert(Rules { before: HashSet::new(), after });
-    character_map.entry(letters[1])
-      .and_modify(|set| { set.before.insert(letters[0]); })
-      .or_insert(Rules { before, after: HashSet::new() });
+  steps.iter().for_each(|rule| match rule.as_slice() {
+    [step] => add_step(&mut step_map, step),
+    [first, second] => {
+      add_step(&mut step_map, first);
+      add_step(&mut step_map, second);
+      step_map.get_mut(first).unwrap().after.insert(second.clone());
+      step_map.get_mut(second).unwrap().before.insert(first.clone());
+    }
+    _ => panic!("A rule needs one or two steps, got {}", rule.len()),
   });
 
-  character_map
+  step_map
 }
 
-fn find_first_candidates(rules: &mut HashMap<char, Rules>) -> Vec<char> {
-  let mut candidates = rules.iter()
+fn find_first_candidates<N: Clone + Eq + Hash + Ord>(rules: &HashMap<N, Rules<N>>, queue: &mut ReadyQueue<N>) {
+  rules.iter()
     .filter(|(_, val)| val.before.is_empty())
-    .map(|(tasks, _)| *tasks)
-    .collect::<Vec<_>>();
-  candidates.sort();
-  candidates
+    .for_each(|(step, _)| queue.push(step));
 }
 
-fn iterate_next_candidate(rules: &mut HashMap<char, Rules>, candidate: char) -> Vec<char> {
-  let next_candidates = rules.get(&candidate).unwrap().after.iter().cloned().collect::<HashSet<char>>();
-  next_candidates.iter()
-    .filter(|&key| {
-      let rules: &mut Rules = rules.get_mut(&key).unwrap();
-      rules.before.remove(&candidate);
+/// Marks `candidate` as done and returns the steps that no longer wait for anything.
+fn iterate_next_candidate<N: Clone + Eq + Hash>(rules: &mut HashMap<N, Rules<N>>, candidate: &N) -> Vec<N> {
+  let next_candidates = rules[candidate].after.iter().cloned().collect::<Vec<N>>();
+  next_candidates.into_iter()
+    .filter(|key| {
+      let rules = rules.get_mut(key).unwrap();
+      rules.before.remove(candidate);
       rules.before.is_empty()
     })
-    .cloned()
     .collect::<Vec<_>>()
 }
 
-fn sort_characters(parsed_data: &[Vec<char>]) -> String {
-  let mut character_mapping = construct_rule_map(&parsed_data);
-  let mut sorted_candidates = find_first_candidates(&mut character_mapping);
+/// Called once no candidates are left. Every step that still waits for another one either
+/// lies on a cycle or waits for a step that does, so walking back from any of them has
+/// to revisit a step.
+fn find_cycle<N: Clone + Eq + Hash + Ord>(rules: &HashMap<N, Rules<N>>) -> Result<(), CycleError<N>> {
+  let mut waiting = rules.iter()
+    .filter(|(_, val)| !val.before.is_empty())
+    .map(|(step, _)| step.clone())
+    .collect::<Vec<_>>();
+  waiting.sort();
+
+  let mut path = match waiting.first() {
+    Some(step) => vec![step.clone()],
+    None => return Ok(()),
+  };
+  loop {
+    let previous = rules[path.last().unwrap()].before.iter().min().unwrap().clone();
+    if let Some(start) = path.iter().position(|seen| *seen == previous) {
+      let mut cycle = path.split_off(start);
+      cycle.reverse();
+      let first = cycle.iter().enumerate().min_by_key(|&(_, step)| step).map(|(idx, _)| idx).unwrap();
+      cycle.rotate_left(first);
+      cycle.push(cycle[0].clone());
+      let unavailable = waiting.into_iter().filter(|step| !cycle.contains(step)).collect();
+      return Err(CycleError { cycle, unavailable });
+    }
+    path.push(previous);
+  }
+}
+
+/// Kahn's algorithm where `tie_break` picks among the steps that are available at the same time.
+fn sort_steps_with<N>(parsed_data: &[Vec<N>], tie_break: &TieBreak<N>) -> Result<Vec<N>, CycleError<N>>
+  where N: Clone + Eq + Hash + Ord {
+  let mut step_mapping = construct_rule_map(parsed_data);
+  let mut queue = ReadyQueue::new(&step_mapping, tie_break);
+  find_first_candidates(&step_mapping, &mut queue);
   let mut result = vec![];
 
-  while !sorted_candidates.is_empty() {
-    let candidate = sorted_candidates.remove(0);
+  while let Some(candidate) = queue.pop() {
+    iterate_next_candidate(&mut step_mapping, &candidate).iter()
+      .for_each(|new_candidate| queue.push(new_candidate));
     result.push(candidate);
-    iterate_next_candidate(&mut character_mapping, candidate).iter()
-      .for_each(|&new_candidate| {
-        sorted_candidates.push(new_candidate);
-      });
-    sorted_candidates.sort();
   }
-  result.iter().cloned().collect::<String>()
+  find_cycle(&step_mapping)?;
+  Ok(result)
+}
+
+/// Single letter steps are written together as in the puzzle, longer names separated by spaces.
+fn join_steps(steps: &[String]) -> String {
+  let separator = if steps.iter().all(|step| step.chars().count() == 1) { "" } else { " " };
+  steps.join(separator)
+}
+
+fn sort_names(parsed_data: &[Rule]) -> Result<String, CycleError<String>> {
+  sort_names_with(parsed_data, &TieBreak::Alphabetical)
+}
+
+fn sort_names_with(parsed_data: &[Rule], tie_break: &TieBreak<String>) -> Result<String, CycleError<String>> {
+  sort_steps_with(parsed_data, tie_break).map(|steps| join_steps(&steps))
+}
+
+/// `alphabetical`, `reverse`, `insertion` or `dependents`, which puts steps that more steps
+/// wait for first.
+fn tie_break_by_name(name: &str, parsed_data: &[Rule]) -> TieBreak<String> {
+  match name {
+    "alphabetical" => TieBreak::Alphabetical,
+    "reverse" => TieBreak::Reverse,
+    "insertion" => TieBreak::InsertionOrder,
+    "dependents" => {
+      let rules = construct_rule_map(parsed_data);
+      let dependents = rules.iter()
+        .map(|(step, rule)| (step.clone(), rule.after.len() as i64))
+        .collect::<HashMap<String, i64>>();
+      TieBreak::Weight(Box::new(move |step| -dependents[step]))
+    }
+    other => panic!("Unknown order {}", other),
+  }
 }
 
 fn main() {
-  let data = read_input_file("./input.txt");
-  let lines = lines_to_vec(&data);
+  let data = read_input_file(&arg_value("--input").unwrap_or_else(|| "./input.txt".to_string()));
+  let format = format_by_name(&arg_value("--format").unwrap_or_else(|| "sentence".to_string()));
+  let parsed_data = parse_rules(&data, format).unwrap_or_else(|errors| {
+    errors.iter().for_each(|error| eprintln!("{}", error));
+    panic!("Parsing failed with {} malformed lines", errors.len())
+  });
 
-  let parsed_data = lines.iter()
-    .map(|&line| parse_input_line(line))
-    .collect::<Vec<_>>();
+  let order = match arg_value("--order") {
+    Some(name) => sort_names_with(&parsed_data, &tie_break_by_name(&name, &parsed_data)),
+    None => sort_names(&parsed_data),
+  }.unwrap_or_else(|e| panic!("Sort error: {}", e));
+  println!("Part one {}", order);
 
-  println!("Part one {}", sort_characters(&parsed_data));
+  let result = schedule(&parsed_data, arg_number("--workers", 5) as usize, arg_number("--base", 60))
+    .unwrap_or_else(|e| panic!("Schedule error: {}", e));
+  println!("Part two {}", result.total_time);
+  if env::args().any(|arg| arg == "--timeline") {
+    println!("Order {}", join_steps(&result.order()));
+    for (worker, assignments) in result.workers.iter().enumerate() {
+      let steps = assignments.iter()
+        .map(|assignment| format!("{} {}-{}", assignment.step, assignment.start, assignment.end))
+        .collect::<Vec<_>>();
+      println!("Worker {}: {}", worker + 1, steps.join(", "));
+    }
+  }
+}
+
+#[allow(dead_code)]
+fn parse_lines(lines: &[&str]) -> Vec<Rule> {
+  parse_rules(&lines.join("\n"), Format::Sentence).unwrap_or_else(|errors| panic!("Parse error: {}", errors[0]))
 }
 
 #[allow(dead_code)]
 fn test_shorthand(lines: Vec<&str>) -> String {
-  let parsed_data = lines.iter()
-    .map(|&line| parse_input_line(line))
-    .collect::<Vec<_>>();
+  let parsed_data = parse_lines(&lines);
 
-  sort_characters(&parsed_data)
+  sort_names(&parsed_data).unwrap_or_else(|e| panic!("Sort error: {}", e))
 }
 
 #[test]
@@ -151,3 +261,82 @@ fn third_test() {
 
   assert_eq!(&test_shorthand(lines), "ACDBEFKLO");
 }
+
+#[test]
+fn cycle_test() {
+  let lines = [
+    "Step B must be finished before step C can begin.",
+    "Step C must be finished before step A can begin.",
+    "Step A must be finished before step F can begin.",
+    "Step F must be finished before step C can begin.",
+    "Step F must be finished before step E can begin.",
+    "Step E must be finished before step G can begin.",
+  ];
+  let parsed_data = parse_lines(&lines);
+
+  let error = sort_names(&parsed_data).unwrap_err();
+  let names = |steps: &str| steps.chars().map(|step| step.to_string()).collect::<Vec<_>>();
+  assert_eq!(error, CycleError { cycle: names("AFCA"), unavailable: names("EG") });
+  assert_eq!(error.to_string(), "steps form a cycle A -> F -> C -> A\n\
+                                 warning: step E never becomes available\n\
+                                 warning: step G never becomes available");
+}
+
+#[test]
+fn tie_break_test() {
+  let lines = [
+    "Step C must be finished before step A can begin.",
+    "Step C must be finished before step F can begin.",
+    "Step A must be finished before step B can begin.",
+    "Step A must be finished before step D can begin.",
+    "Step B must be finished before step E can begin.",
+    "Step D must be finished before step E can begin.",
+    "Step F must be finished before step E can begin.",
+  ];
+  let parsed_data = parse_lines(&lines);
+
+  let sorted = |name| sort_names_with(&parsed_data, &tie_break_by_name(name, &parsed_data)).unwrap();
+  assert_eq!(sorted("alphabetical"), "CABDFE");
+  assert_eq!(sorted("reverse"), "CFADBE");
+  assert_eq!(sorted("insertion"), "CAFBDE");
+  assert_eq!(sorted("dependents"), "CABDFE");
+}
+
+#[test]
+fn generic_steps_test() {
+  // Step n waits for n / 2 and for n - 3, more steps than there are letters.
+  let parsed_data = (1..60u32)
+    .flat_map(|step| vec![vec![step / 2, step], vec![step.saturating_sub(3), step]])
+    .filter(|pair| pair[0] != pair[1])
+    .collect::<Vec<_>>();
+
+  let sorted = sort_steps_with(&parsed_data, &TieBreak::Alphabetical).unwrap();
+  assert_eq!(sorted, (0..60).collect::<Vec<u32>>());
+  let reversed = sort_steps_with(&parsed_data, &TieBreak::Reverse).unwrap();
+  assert_eq!(&reversed[..6], &[0, 1, 3, 6, 2, 5]);
+  let by_weight = sort_steps_with(&parsed_data, &TieBreak::Weight(Box::new(|&step| -i64::from(step % 5)))).unwrap();
+  let position = |step: &u32| by_weight.iter().position(|sorted| sorted == step).unwrap();
+  assert!(parsed_data.iter().all(|pair| position(&pair[0]) < position(&pair[1])));
+  assert_eq!(&by_weight[..4], &[0, 1, 3, 2]);
+
+  let mut looped = parsed_data.clone();
+  looped.push(vec![40, 7]);
+  let error = sort_steps_with(&looped, &TieBreak::Alphabetical).unwrap_err();
+  assert_eq!(error.cycle.first(), error.cycle.last());
+  assert!(error.cycle.contains(&40) && error.cycle.contains(&7));
+}
+
+#[test]
+fn named_steps_test() {
+  let lines = [
+    "Step fetch must be finished before step Build can begin.",
+    "Step Build must be finished before step A1 can begin.",
+    "Step Build must be finished before step A10 can begin.",
+    "Step A10 must be finished before step A2 can begin.",
+  ];
+  assert_eq!(sort_names(&parse_lines(&lines)).unwrap(), "fetch Build A1 A10 A2");
+
+  let makefile = "app: main.o util.o\nmain.o: main.c\nutil.o: util.c\n\tcc -c util.c\n";
+  let parsed_data = parse_rules(makefile, Format::Makefile).unwrap();
+  assert_eq!(sort_names(&parsed_data).unwrap(), "main.c main.o util.c util.o app");
+}
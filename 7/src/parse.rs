@@ -0,0 +1,482 @@
+use std::fmt;
+
+/// A rule is `[before, after]`, or `[step]` for a step that is mentioned without dependencies.
+pub type Rule = Vec<String>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+  /// `Step X must be finished before step Y can begin.`, as in the puzzle.
+  Sentence,
+  /// `X Y` or `X -> Y` per line, or a lone `X`.
+  EdgeList,
+  /// `target: dependencies`. Recipes, including one after `;`, variables and special targets are
+  /// skipped, and lines ending in `\` are joined with the next one.
+  Makefile,
+  /// A `digraph` with `X -> Y` edges and plain node statements. Attributes, comments and subgraph
+  /// braces are skipped.
+  Dot,
+}
+
+/// `sentence`, `edges`, `make` or `dot`.
+pub fn format_by_name(name: &str) -> Format {
+  match name {
+    "sentence" => Format::Sentence,
+    "edges" => Format::EdgeList,
+    "make" => Format::Makefile,
+    "dot" => Format::Dot,
+    other => panic!("Unknown format {}", other),
+  }
+}
+
+enum Token {
+  Word(&'static str),
+  Step,
+}
+
+const SENTENCE: [Token; 10] = [
+  Token::Word("Step"), Token::Step, Token::Word("must"), Token::Word("be"), Token::Word("finished"),
+  Token::Word("before"), Token::Word("step"), Token::Step, Token::Word("can"), Token::Word("begin."),
+];
+
+fn parse_sentence(line: &str) -> Result<Vec<Rule>, String> {
+  let mut words = line.split_whitespace();
+  let mut steps = vec![];
+  for token in SENTENCE.iter() {
+    let word = words.next();
+    match (token, word) {
+      (Token::Word(expected), Some(word)) if word == *expected => (),
+      (Token::Word(expected), Some(word)) => return Err(format!("expected `{}` but found `{}`", expected, word)),
+      (Token::Word(expected), None) => return Err(format!("expected `{}` but the line ended", expected)),
+      (Token::Step, Some(word)) => steps.push(word.to_string()),
+      (Token::Step, None) => return Err("expected a step name but the line ended".to_string()),
+    }
+  }
+  if let Some(word) = words.next() {
+    return Err(format!("unexpected `{}` after the end of the rule", word));
+  }
+  Ok(vec![steps])
+}
+
+fn parse_edge(line: &str) -> Result<Vec<Rule>, String> {
+  let steps = line.split_whitespace().filter(|&word| word != "->").collect::<Vec<_>>();
+  match steps.as_slice() {
+    [step] => Ok(vec![vec![step.to_string()]]),
+    [before, after] => Ok(vec![vec![before.to_string(), after.to_string()]]),
+    _ => Err(format!("expected one or two step names but found {}", steps.len())),
+  }
+}
+
+fn parse_make(line: &str) -> Result<Vec<Rule>, String> {
+  let line = line.split(';').next().unwrap_or(line);
+  if line.starts_with('\t') || line.contains('=') {
+    return Ok(vec![]);
+  }
+  let (targets, dependencies) = match line.find(':') {
+    Some(idx) => (&line[..idx], &line[idx + 1..]),
+    None => return Err("expected `target: dependencies`".to_string()),
+  };
+  let targets = targets.split_whitespace().filter(|target| !target.starts_with('.')).collect::<Vec<_>>();
+  if targets.is_empty() && !line.trim_start().starts_with('.') {
+    return Err("missing target before `:`".to_string());
+  }
+  let dependencies = dependencies.split_whitespace().collect::<Vec<_>>();
+  Ok(targets.iter()
+    .flat_map(|&target| match dependencies.is_empty() {
+      true => vec![vec![target.to_string()]],
+      false => dependencies.iter().map(|&dependency| vec![dependency.to_string(), target.to_string()]).collect(),
+    })
+    .collect())
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum DotToken {
+  Id(String),
+  Quoted(String),
+  Edge,
+  UndirectedEdge,
+  Symbol(char),
+}
+
+fn describe(token: Option<&DotToken>) -> String {
+  match token {
+    Some(DotToken::Id(id)) => format!("`{}`", id),
+    Some(DotToken::Quoted(id)) => format!("`\"{}\"`", id),
+    Some(DotToken::Edge) => "`->`".to_string(),
+    Some(DotToken::UndirectedEdge) => "`--`".to_string(),
+    Some(DotToken::Symbol(symbol)) => format!("`{}`", symbol),
+    None => "the end of the input".to_string(),
+  }
+}
+
+/// Splits a DOT graph into tokens with their line numbers. Comments are dropped, and a quoted ID
+/// keeps any spaces, `->` or `;` it contains.
+fn dot_tokens(input: &str) -> Result<Vec<(usize, DotToken)>, ParseError> {
+  let mut tokens = vec![];
+  let mut chars = input.chars().peekable();
+  let mut line = 1;
+  while let Some(c) = chars.next() {
+    let start = line;
+    let error = |message: &str| ParseError { line: start, message: message.to_string() };
+    match c {
+      '\n' => line += 1,
+      c if c.is_whitespace() => (),
+      c if c == '#' || (c == '/' && chars.peek() == Some(&'/')) => {
+        while chars.peek().is_some_and(|&c| c != '\n') {
+          chars.next();
+        }
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut previous = ' ';
+        loop {
+          match chars.next() {
+            Some('/') if previous == '*' => break,
+            Some(c) => {
+              line += (c == '\n') as usize;
+              previous = c;
+            }
+            None => return Err(error("unterminated `/*` comment")),
+          }
+        }
+      }
+      '"' => {
+        let mut id = String::new();
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') if chars.peek() == Some(&'"') => id.push(chars.next().unwrap_or('"')),
+            Some('\\') if chars.peek() == Some(&'\n') => {
+              chars.next();
+              line += 1;
+            }
+            Some(c) => {
+              line += (c == '\n') as usize;
+              id.push(c);
+            }
+            None => return Err(error("unterminated quoted name")),
+          }
+        }
+        tokens.push((start, DotToken::Quoted(id)));
+      }
+      '-' if chars.peek() == Some(&'>') => {
+        chars.next();
+        tokens.push((start, DotToken::Edge));
+      }
+      '-' if chars.peek() == Some(&'-') => {
+        chars.next();
+        tokens.push((start, DotToken::UndirectedEdge));
+      }
+      '{' | '}' | '[' | ']' | ';' | ',' | '=' | ':' => tokens.push((start, DotToken::Symbol(c))),
+      c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+        let mut id = c.to_string();
+        while let Some(&c) = chars.peek().filter(|&&c| c.is_alphanumeric() || c == '_' || c == '.') {
+          id.push(c);
+          chars.next();
+        }
+        tokens.push((start, DotToken::Id(id)));
+      }
+      other => return Err(error(&format!("unexpected `{}`", other))),
+    }
+  }
+  Ok(tokens)
+}
+
+struct DotParser {
+  tokens: Vec<(usize, DotToken)>,
+  pos: usize,
+}
+
+impl DotParser {
+  fn peek(&self) -> Option<&DotToken> {
+    self.tokens.get(self.pos).map(|(_, token)| token)
+  }
+
+  fn line(&self) -> usize {
+    self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |&(line, _)| line)
+  }
+
+  fn error(&self, message: String) -> ParseError {
+    ParseError { line: self.line(), message }
+  }
+
+  /// Keywords are case-insensitive and never quoted.
+  fn keyword(&self, word: &str) -> bool {
+    matches!(self.peek(), Some(DotToken::Id(id)) if id.eq_ignore_ascii_case(word))
+  }
+
+  fn symbol(&self, symbol: char) -> bool {
+    self.peek() == Some(&DotToken::Symbol(symbol))
+  }
+
+  fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+    if !self.symbol(symbol) {
+      return Err(self.error(format!("expected `{}` but found {}", symbol, describe(self.peek()))));
+    }
+    self.pos += 1;
+    Ok(())
+  }
+
+  fn id(&mut self) -> Result<String, ParseError> {
+    let id = match self.peek() {
+      Some(DotToken::Id(id)) | Some(DotToken::Quoted(id)) if !id.is_empty() => id.clone(),
+      token => return Err(self.error(format!("expected a node name but found {}", describe(token)))),
+    };
+    self.pos += 1;
+    Ok(id)
+  }
+
+  /// A node name, skipping any `:port:compass` after it.
+  fn node_id(&mut self) -> Result<String, ParseError> {
+    let id = self.id()?;
+    while self.symbol(':') {
+      self.pos += 1;
+      self.id()?;
+    }
+    Ok(id)
+  }
+
+  /// `[strict] digraph [name] {`, which may span several lines.
+  fn header(&mut self) -> Result<(), ParseError> {
+    if self.keyword("strict") {
+      self.pos += 1;
+    }
+    if !self.keyword("digraph") {
+      return Err(self.error(format!("expected `digraph` but found {}", describe(self.peek()))));
+    }
+    self.pos += 1;
+    if !self.symbol('{') {
+      self.id()?;
+    }
+    self.expect('{')
+  }
+
+  /// Skips any number of `[name=value, ...]` lists.
+  fn attributes(&mut self) -> Result<(), ParseError> {
+    while self.symbol('[') {
+      while !self.symbol(']') {
+        if self.peek().is_none() {
+          return Err(self.error("expected `]` but the input ended".to_string()));
+        }
+        self.pos += 1;
+      }
+      self.pos += 1;
+    }
+    Ok(())
+  }
+
+  /// Enters `subgraph name {` or an anonymous `{`, whose statements are then read as if the braces
+  /// were not there.
+  fn subgraph(&mut self) -> bool {
+    let start = self.pos;
+    if self.keyword("subgraph") {
+      self.pos += 1;
+      if matches!(self.peek(), Some(DotToken::Id(_)) | Some(DotToken::Quoted(_))) {
+        self.pos += 1;
+      }
+    }
+    if self.symbol('{') {
+      self.pos += 1;
+      return true;
+    }
+    self.pos = start;
+    false
+  }
+
+  /// An edge chain, a node, or a graph, node or edge default or `name = value`, which add nothing.
+  fn statement(&mut self) -> Result<Vec<Rule>, ParseError> {
+    if ["graph", "node", "edge"].iter().any(|word| self.keyword(word)) {
+      self.pos += 1;
+      return self.attributes().map(|_| vec![]);
+    }
+    let mut steps = vec![self.node_id()?];
+    if self.symbol('=') {
+      self.pos += 1;
+      return self.id().map(|_| vec![]);
+    }
+    loop {
+      match self.peek() {
+        Some(DotToken::Edge) => {
+          self.pos += 1;
+          steps.push(self.node_id()?);
+        }
+        Some(DotToken::UndirectedEdge) => return Err(self.error("undirected edge `--` in a step graph".to_string())),
+        _ => break,
+      }
+    }
+    self.attributes()?;
+    match steps.len() {
+      1 => Ok(vec![steps]),
+      _ => Ok(steps.windows(2).map(|pair| pair.to_vec()).collect()),
+    }
+  }
+
+  /// Moves past the rest of a malformed statement: up to the next `;` or `}` or the next line.
+  fn skip_statement(&mut self, line: usize) {
+    while self.tokens.get(self.pos).is_some_and(|(at, _)| *at == line) && !self.symbol(';') && !self.symbol('}') {
+      self.pos += 1;
+    }
+  }
+}
+
+/// Parses one `digraph` as a whole, so the header, statements and comments may be laid out over
+/// lines freely. Statements are separated by `;` or by new lines.
+fn parse_dot(input: &str) -> Result<Vec<Rule>, Vec<ParseError>> {
+  let mut parser = DotParser { tokens: dot_tokens(input).map_err(|error| vec![error])?, pos: 0 };
+  parser.header().map_err(|error| vec![error])?;
+  let mut rules = vec![];
+  let mut errors = vec![];
+  let mut depth = 0;
+  loop {
+    match parser.peek().cloned() {
+      None => {
+        errors.push(parser.error("expected `}` but the input ended".to_string()));
+        break;
+      }
+      Some(DotToken::Symbol('}')) => {
+        parser.pos += 1;
+        if depth == 0 {
+          break;
+        }
+        depth -= 1;
+      }
+      Some(DotToken::Symbol(';')) | Some(DotToken::Symbol(',')) => parser.pos += 1,
+      _ if parser.subgraph() => depth += 1,
+      _ => {
+        let line = parser.line();
+        match parser.statement() {
+          Ok(parsed) => rules.extend(parsed),
+          Err(error) => {
+            errors.push(error);
+            parser.skip_statement(line);
+          }
+        }
+      }
+    }
+  }
+  if parser.peek().is_some() {
+    errors.push(parser.error(format!("unexpected {} after the closing `}}`", describe(parser.peek()))));
+  }
+  if errors.is_empty() { Ok(rules) } else { Err(errors) }
+}
+
+/// Numbers the lines, joining a Makefile line that ends in `\` with the next one. A joined line
+/// keeps the number of its first line.
+fn logical_lines(input: &str, format: Format) -> Vec<(usize, String)> {
+  let mut lines: Vec<(usize, String)> = vec![];
+  let mut continued = false;
+  for (idx, line) in input.lines().enumerate() {
+    let text = match format {
+      Format::Makefile => line.strip_suffix('\\'),
+      _ => None,
+    };
+    match (continued, lines.last_mut()) {
+      (true, Some((_, joined))) => {
+        joined.push(' ');
+        joined.push_str(text.unwrap_or(line).trim_start());
+      }
+      _ => lines.push((idx + 1, text.unwrap_or(line).to_string())),
+    }
+    continued = text.is_some();
+  }
+  lines
+}
+
+/// Parses every line and reports every malformed one. Blank lines and `#` comments are skipped
+/// in all but the sentence format, where only blank lines are. A DOT graph is parsed as a whole.
+pub fn parse_rules(input: &str, format: Format) -> Result<Vec<Rule>, Vec<ParseError>> {
+  let parse_line = match format {
+    Format::Sentence => parse_sentence,
+    Format::EdgeList => parse_edge,
+    Format::Makefile => parse_make,
+    Format::Dot => return parse_dot(input),
+  };
+  let mut rules = vec![];
+  let mut errors = vec![];
+  for (line, text) in logical_lines(input, format) {
+    if text.trim().is_empty() || (format != Format::Sentence && text.trim_start().starts_with('#')) {
+      continue;
+    }
+    match parse_line(&text) {
+      Ok(parsed) => rules.extend(parsed),
+      Err(message) => errors.push(ParseError { line, message }),
+    }
+  }
+  if errors.is_empty() { Ok(rules) } else { Err(errors) }
+}
+
+#[allow(dead_code)]
+fn rules(pairs: &[&[&str]]) -> Vec<Rule> {
+  pairs.iter().map(|rule| rule.iter().map(|step| step.to_string()).collect()).collect()
+}
+
+#[test]
+fn sentence_test() {
+  let input = "Step Build must be finished before step A1 can begin.\n\n\
+               Step C must finished before step A can begin.\n\
+               Step C must be finished before step A can\n\
+               Step X must be finished before step Y can begin. now\n";
+  let errors = parse_rules(input, Format::Sentence).unwrap_err();
+  assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![
+    "line 3: expected `be` but found `finished`",
+    "line 4: expected `begin.` but the line ended",
+    "line 5: unexpected `now` after the end of the rule",
+  ]);
+  let parsed = parse_rules("Step Build must be finished before step A1 can begin.", Format::Sentence).unwrap();
+  assert_eq!(parsed, rules(&[&["Build", "A1"]]));
+}
+
+#[test]
+fn formats_test() {
+  let edges = "# comment\nfetch -> build\nbuild test\nlint\n";
+  assert_eq!(parse_rules(edges, Format::EdgeList).unwrap(), rules(&[&["fetch", "build"], &["build", "test"], &["lint"]]));
+  assert_eq!(parse_rules("a b c", Format::EdgeList).unwrap_err()[0].to_string(),
+             "line 1: expected one or two step names but found 3");
+
+  let makefile = "CC := cc\n.PHONY: all\nall: app docs\napp: main.o\n\tcc -o app main.o\nmain.o:\nbroken\n";
+  assert_eq!(parse_rules(makefile, Format::Makefile).unwrap_err(),
+             vec![ParseError { line: 7, message: "expected `target: dependencies`".to_string() }]);
+  assert_eq!(parse_rules(&makefile.replace("broken\n", ""), Format::Makefile).unwrap(),
+             rules(&[&["app", "all"], &["docs", "all"], &["main.o", "app"], &["main.o"]]));
+  let makefile = "app: main.o \\\n     util.o ; cc -o app main.o util.o\nmain.o: main.c; cc -DDEBUG=1 -c main.c\n\
+                  util.o: \\\n  util.c\nbroken\n";
+  assert_eq!(parse_rules(makefile, Format::Makefile).unwrap_err(),
+             vec![ParseError { line: 6, message: "expected `target: dependencies`".to_string() }]);
+  assert_eq!(parse_rules(&makefile.replace("broken\n", ""), Format::Makefile).unwrap(),
+             rules(&[&["main.o", "app"], &["util.o", "app"], &["main.c", "main.o"], &["util.c", "util.o"]]));
+
+  let dot = "digraph steps {\n  node [shape=box];\n  rankdir=LR;\n  \"fetch sources\" -> build -> test;\n  docs\n}\n";
+  assert_eq!(parse_rules(dot, Format::Dot).unwrap(),
+             rules(&[&["fetch sources", "build"], &["build", "test"], &["docs"]]));
+  assert_eq!(parse_rules("digraph {\na -- b\n}", Format::Dot).unwrap_err()[0].line, 2);
+  assert_eq!(format_by_name("make"), Format::Makefile);
+}
+
+#[test]
+fn dot_layout_test() {
+  let dot = "strict digraph G\n{\n  /* fetch -> test;\n     fetch -> lint */\n  fetch -> build // build -> lint\n  \
+             \"x->y\" -> \"say \\\"hi\\\"\"; subgraph cluster_docs { docs:n } }\n";
+  assert_eq!(parse_rules(dot, Format::Dot).unwrap(),
+             rules(&[&["fetch", "build"], &["x->y", "say \"hi\""], &["docs"]]));
+
+  let broken = "digraph {\n  a -- b\n  c -> ; d\n  e [color=red] -> f\n  g\n";
+  assert_eq!(parse_rules(broken, Format::Dot).unwrap_err().iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![
+    "line 2: undirected edge `--` in a step graph",
+    "line 3: expected a node name but found `;`",
+    "line 4: expected a node name but found `->`",
+    "line 5: expected `}` but the input ended",
+  ]);
+  assert_eq!(parse_rules("graph { a }", Format::Dot).unwrap_err()[0].message, "expected `digraph` but found `graph`");
+  assert_eq!(parse_rules("digraph { \"a }", Format::Dot).unwrap_err()[0].message, "unterminated quoted name");
+}
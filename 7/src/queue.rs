@@ -0,0 +1,54 @@
+use crate::Rules;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+
+/// Decides which of the available steps goes first.
+pub enum TieBreak<N> {
+  /// Smallest step first, as in the puzzle.
+  Alphabetical,
+  /// Largest step first.
+  Reverse,
+  /// Smallest weight first, alphabetical among equal weights.
+  Weight(Box<dyn Fn(&N) -> i64>),
+  /// The step that appears first in the rules goes first.
+  InsertionOrder,
+}
+
+impl<N: Ord> TieBreak<N> {
+  /// `Less` when step `a`, first seen at `a_order`, goes before step `b`.
+  fn compare(&self, (a, a_order): (&N, usize), (b, b_order): (&N, usize)) -> Ordering {
+    match self {
+      TieBreak::Alphabetical => a.cmp(b),
+      TieBreak::Reverse => b.cmp(a),
+      TieBreak::Weight(weight) => weight(a).cmp(&weight(b)).then_with(|| a.cmp(b)),
+      TieBreak::InsertionOrder => a_order.cmp(&b_order),
+    }
+  }
+}
+
+/// Available steps in a binary heap. Every step gets its rank up front, so the heap only
+/// compares numbers and the tie-break is evaluated once per step.
+pub struct ReadyQueue<N> {
+  heap: BinaryHeap<Reverse<usize>>,
+  ranked: Vec<N>,
+  ranks: HashMap<N, usize>,
+}
+
+impl<N: Clone + Eq + Hash + Ord> ReadyQueue<N> {
+  pub fn new(rules: &HashMap<N, Rules<N>>, tie_break: &TieBreak<N>) -> Self {
+    let mut ranked = rules.iter().map(|(step, rule)| (step, rule.order)).collect::<Vec<_>>();
+    ranked.sort_by(|&a, &b| tie_break.compare(a, b));
+    let ranked = ranked.into_iter().map(|(step, _)| step.clone()).collect::<Vec<N>>();
+    let ranks = ranked.iter().enumerate().map(|(rank, step)| (step.clone(), rank)).collect();
+    ReadyQueue { heap: BinaryHeap::new(), ranked, ranks }
+  }
+
+  pub fn push(&mut self, step: &N) {
+    self.heap.push(Reverse(self.ranks[step]));
+  }
+
+  pub fn pop(&mut self) -> Option<N> {
+    self.heap.pop().map(|Reverse(rank)| self.ranked[rank].clone())
+  }
+}
@@ -0,0 +1,108 @@
+use crate::parse::Rule;
+use crate::queue::{ReadyQueue, TieBreak};
+use crate::{construct_rule_map, find_cycle, find_first_candidates, iterate_next_candidate, CycleError};
+use std::hash::Hash;
+
+/// One step done by a worker, from `start` until just before `end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment<N> {
+  pub step: N,
+  pub start: u32,
+  pub end: u32,
+}
+
+#[derive(Debug)]
+pub struct Schedule<N> {
+  pub total_time: u32,
+  /// The steps each worker did, in the order they were done.
+  pub workers: Vec<Vec<Assignment<N>>>,
+}
+
+impl<N: Clone + Ord> Schedule<N> {
+  /// Steps in the order they were finished, lower worker numbers first on ties.
+  pub fn order(&self) -> Vec<N> {
+    let mut assignments = self.workers.iter()
+      .enumerate()
+      .flat_map(|(worker, done)| done.iter().map(move |assignment| (assignment.end, worker, assignment.step.clone())))
+      .collect::<Vec<_>>();
+    assignments.sort();
+    assignments.into_iter().map(|(_, _, step)| step).collect()
+  }
+}
+
+/// `base` seconds plus the position of each letter of the name in the alphabet, so `A` takes
+/// `base + 1` and `AB` takes `base + 3`. Other characters take no time.
+pub fn step_duration(step: &str, base: u32) -> u32 {
+  base + step.chars()
+    .filter(|letter| letter.is_ascii_alphabetic())
+    .map(|letter| letter.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    .sum::<u32>()
+}
+
+pub fn schedule(parsed_data: &[Rule], workers: usize, base: u32) -> Result<Schedule<String>, CycleError<String>> {
+  schedule_with(parsed_data, workers, &TieBreak::Alphabetical, |step| step_duration(step, base))
+}
+
+/// Simulates `workers` workers that always pick the first available step by `tie_break`.
+/// A step becomes available once every step before it has been finished.
+pub fn schedule_with<N, F>(parsed_data: &[Vec<N>], workers: usize, tie_break: &TieBreak<N>, duration: F)
+  -> Result<Schedule<N>, CycleError<N>>
+  where N: Clone + Eq + Hash + Ord, F: Fn(&N) -> u32 {
+  let mut step_mapping = construct_rule_map(parsed_data);
+  let mut available = ReadyQueue::new(&step_mapping, tie_break);
+  find_first_candidates(&step_mapping, &mut available);
+  let mut running: Vec<Option<Assignment<N>>> = vec![None; workers.max(1)];
+  let mut timeline = vec![vec![]; running.len()];
+  let mut time = 0;
+
+  loop {
+    for slot in running.iter_mut().filter(|slot| slot.is_none()) {
+      match available.pop() {
+        Some(step) => *slot = Some(Assignment { start: time, end: time + duration(&step), step }),
+        None => break,
+      }
+    }
+
+    time = match running.iter().flatten().map(|assignment| assignment.end).min() {
+      Some(end) => end,
+      None => break,
+    };
+
+    for (worker, slot) in running.iter_mut().enumerate() {
+      if slot.as_ref().is_some_and(|assignment| assignment.end == time) {
+        let assignment = slot.take().unwrap();
+        iterate_next_candidate(&mut step_mapping, &assignment.step).iter()
+          .for_each(|step| available.push(step));
+        timeline[worker].push(assignment);
+      }
+    }
+  }
+
+  find_cycle(&step_mapping)?;
+  Ok(Schedule { total_time: time, workers: timeline })
+}
+
+#[test]
+fn schedule_test() {
+  let lines = [
+    "Step C must be finished before step A can begin.",
+    "Step C must be finished before step F can begin.",
+    "Step A must be finished before step B can begin.",
+    "Step A must be finished before step D can begin.",
+    "Step B must be finished before step E can begin.",
+    "Step D must be finished before step E can begin.",
+    "Step F must be finished before step E can begin.",
+  ];
+  let parsed_data = crate::parse_lines(&lines);
+
+  let result = schedule(&parsed_data, 2, 0).unwrap();
+  assert_eq!(result.total_time, 15);
+  assert_eq!(result.order().concat(), "CABFDE");
+  assert_eq!(result.workers[1], vec![Assignment { step: "F".to_string(), start: 3, end: 9 }]);
+  assert_eq!(result.workers[0].last(), Some(&Assignment { step: "E".to_string(), start: 10, end: 15 }));
+
+  assert_eq!(schedule(&parsed_data, 1, 0).unwrap().order().concat(), "CABDFE");
+  assert_eq!(schedule(&parsed_data, 1, 60).unwrap().total_time, 6 * 60 + 21);
+  assert_eq!(step_duration("Z", 60), 86);
+  assert_eq!(step_duration("A1b", 0), 3);
+}
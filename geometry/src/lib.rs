@@ -0,0 +1,196 @@
+use std::ops::{Index, IndexMut};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+  pub x: i32,
+  pub y: i32,
+}
+
+impl Point {
+  pub fn new(x: i32, y: i32) -> Self {
+    Point { x, y }
+  }
+
+  /// The four orthogonal neighbours: up, left, down and right.
+  pub fn neighbours(self) -> [Point; 4] {
+    [Point::new(self.x, self.y - 1),
+     Point::new(self.x - 1, self.y),
+     Point::new(self.x, self.y + 1),
+     Point::new(self.x + 1, self.y)]
+  }
+}
+
+impl From<(i32, i32)> for Point {
+  fn from((x, y): (i32, i32)) -> Self {
+    Point::new(x, y)
+  }
+}
+
+/// An axis-aligned rectangle including both corners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+  pub min: Point,
+  pub max: Point,
+}
+
+impl Rect {
+  pub fn new(min: Point, max: Point) -> Self {
+    Rect { min, max }
+  }
+
+  /// A rectangle with its top-left corner at `pos` covering `width` × `height` cells.
+  pub fn with_size(pos: Point, width: usize, height: usize) -> Self {
+    Rect::new(pos, Point::new(pos.x + width as i32 - 1, pos.y + height as i32 - 1))
+  }
+
+  /// The smallest rectangle containing all points, or `None` without points.
+  pub fn bounding<I: IntoIterator<Item=Point>>(points: I) -> Option<Self> {
+    points.into_iter().fold(None, |bounds, point| match bounds {
+      None => Some(Rect::new(point, point)),
+      Some(Rect { min, max }) => Some(Rect::new(Point::new(min.x.min(point.x), min.y.min(point.y)),
+                                                Point::new(max.x.max(point.x), max.y.max(point.y)))),
+    })
+  }
+
+  pub fn width(&self) -> usize {
+    (self.max.x - self.min.x + 1).max(0) as usize
+  }
+
+  pub fn height(&self) -> usize {
+    (self.max.y - self.min.y + 1).max(0) as usize
+  }
+
+  pub fn area(&self) -> usize {
+    self.width() * self.height()
+  }
+
+  pub fn contains(&self, point: Point) -> bool {
+    (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+  }
+
+  pub fn on_border(&self, point: Point) -> bool {
+    self.contains(point) &&
+      (point.x == self.min.x || point.x == self.max.x || point.y == self.min.y || point.y == self.max.y)
+  }
+
+  /// Grows the rectangle by `margin` cells on every side.
+  pub fn expand(&self, margin: i32) -> Self {
+    Rect::new(Point::new(self.min.x - margin, self.min.y - margin),
+              Point::new(self.max.x + margin, self.max.y + margin))
+  }
+
+  pub fn overlaps(&self, other: &Rect) -> bool {
+    self.min.x <= other.max.x && other.min.x <= self.max.x && self.min.y <= other.max.y && other.min.y <= self.max.y
+  }
+
+  /// Every point of the rectangle, row by row.
+  pub fn points(self) -> impl Iterator<Item=Point> {
+    (self.min.y..=self.max.y).flat_map(move |y| (self.min.x..=self.max.x).map(move |x| Point::new(x, y)))
+  }
+}
+
+/// A dense grid with one value per point of its bounds, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+  bounds: Rect,
+  cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+  pub fn new(bounds: Rect, value: T) -> Self {
+    Grid { bounds, cells: vec![value; bounds.area()] }
+  }
+}
+
+impl<T> Grid<T> {
+  pub fn from_fn<F: FnMut(Point) -> T>(bounds: Rect, f: F) -> Self {
+    Grid { bounds, cells: bounds.points().map(f).collect() }
+  }
+
+  pub fn bounds(&self) -> Rect {
+    self.bounds
+  }
+
+  fn offset(&self, point: Point) -> Option<usize> {
+    if self.bounds.contains(point) {
+      let (column, row) = ((point.x - self.bounds.min.x) as usize, (point.y - self.bounds.min.y) as usize);
+      Some(row * self.bounds.width() + column)
+    } else {
+      None
+    }
+  }
+
+  pub fn get(&self, point: Point) -> Option<&T> {
+    self.offset(point).map(|offset| &self.cells[offset])
+  }
+
+  pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+    match self.offset(point) {
+      Some(offset) => Some(&mut self.cells[offset]),
+      None => None,
+    }
+  }
+
+  /// Points and values row by row.
+  pub fn iter(&self) -> impl Iterator<Item=(Point, &T)> {
+    self.bounds.points().zip(self.cells.iter())
+  }
+
+  pub fn values(&self) -> impl Iterator<Item=&T> {
+    self.cells.iter()
+  }
+
+  pub fn rows(&self) -> impl Iterator<Item=&[T]> {
+    self.cells.chunks(self.bounds.width().max(1))
+  }
+
+  /// The orthogonal neighbours of `point` that lie inside the grid.
+  pub fn neighbours(&self, point: Point) -> impl Iterator<Item=(Point, &T)> {
+    IntoIterator::into_iter(point.neighbours())
+      .filter_map(move |neighbour| self.get(neighbour).map(|value| (neighbour, value)))
+  }
+}
+
+impl<T> Index<Point> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, point: Point) -> &T {
+    self.get(point).unwrap_or_else(|| panic!("Point {:?} outside of {:?}", point, self.bounds))
+  }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+  fn index_mut(&mut self, point: Point) -> &mut T {
+    let bounds = self.bounds;
+    self.get_mut(point).unwrap_or_else(|| panic!("Point {:?} outside of {:?}", point, bounds))
+  }
+}
+
+#[test]
+fn rect_test() {
+  let rect = Rect::bounding(vec![Point::new(3, -1), Point::new(-2, 4), Point::new(0, 0)]).unwrap();
+  assert_eq!(rect, Rect::new(Point::new(-2, -1), Point::new(3, 4)));
+  assert_eq!((rect.width(), rect.height(), rect.area()), (6, 6, 36));
+  assert!(rect.on_border(Point::new(3, 2)));
+  assert!(!rect.on_border(Point::new(2, 2)));
+  assert!(!rect.contains(Point::new(4, 2)));
+  assert_eq!(rect.expand(1).min, Point::new(-3, -2));
+  assert_eq!(Rect::with_size(Point::new(1, 3), 4, 2).max, Point::new(4, 4));
+  assert!(rect.overlaps(&Rect::with_size(Point::new(3, 4), 1, 1)));
+  assert!(!rect.overlaps(&Rect::with_size(Point::new(4, 4), 1, 1)));
+  assert_eq!(Rect::bounding(vec![]), None);
+}
+
+#[test]
+fn grid_test() {
+  let bounds = Rect::new(Point::new(-1, 10), Point::new(1, 11));
+  let mut grid = Grid::from_fn(bounds, |point| point.x * 100 + point.y);
+  assert_eq!(grid[Point::new(-1, 11)], -89);
+  assert_eq!(grid.get(Point::new(2, 10)), None);
+  grid[Point::new(0, 10)] = 0;
+  assert_eq!(grid.rows().map(|row| row.to_vec()).collect::<Vec<_>>(), vec![vec![-90, 0, 110], vec![-89, 11, 111]]);
+  let neighbours = grid.neighbours(Point::new(-1, 10)).map(|(point, &value)| (point.x, point.y, value)).collect::<Vec<_>>();
+  assert_eq!(neighbours, vec![(-1, 11, -89), (0, 10, 0)]);
+  assert_eq!(grid.iter().nth(4).map(|(point, _)| point), Some(Point::new(0, 11)));
+  assert_eq!(Grid::new(bounds, '.').values().count(), 6);
+}